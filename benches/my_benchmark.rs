@@ -1,48 +1,86 @@
-use ahash::AHashMap;
 use chogalyzer::generation::get_magic_rules;
 use chogalyzer::stats::analyze;
 use chogalyzer::stats::bigram_stats::{bigram_stats, scissor, skipgram_stats};
 use chogalyzer::stats::trigram_stats::trigram_stat;
 use chogalyzer::*;
-use chogalyzer::{load_layout, load_magic_rules};
+use chogalyzer::{
+    config::Config,
+    corpus::{load_corpora, Corpus, Normalisation},
+    load_layout, load_magic_rules, Directories,
+};
 use diol::prelude::*;
-use std::fs;
 
-fn main() -> std::io::Result<()> {
-    let mut bench = Bench::new(BenchConfig::from_args()?);
+fn main() -> eyre::Result<()> {
+    let bench = Bench::from_args()?;
 
-    bench.register(bench_bigram_stats, ["de", "ey", "er", "li", "ex"]);
-    bench.register(bench_scissor, ["ex", "li", "er"]);
-    bench.register(bench_skipgram_stats, ["eda", "tmn", "thn", "elr", "y.r"]);
-    bench.register(bench_trigram_stats, ["you", "thr", "ale", "atr"]);
-    bench.register(bench_analyse, ["analyze", "sfb"]);
-    bench.register(bench_get_magic_rules, [1, 10, 20]);
+    bench.register(
+        "bigram_stats",
+        bench_bigram_stats,
+        ["de", "ey", "er", "li", "ex"],
+    );
+    bench.register("scissor", bench_scissor, ["ex", "li", "er"]);
+    bench.register(
+        "skipgram_stats",
+        bench_skipgram_stats,
+        ["eda", "tmn", "thn", "elr", "y.r"],
+    );
+    bench.register(
+        "trigram_stats",
+        bench_trigram_stats,
+        ["you", "thr", "ale", "atr"],
+    );
+    bench.register("analyse", bench_analyse, ["analyze", "sfb"]);
+    bench.register("get_magic_rules", bench_get_magic_rules, [1, 10, 20]);
     bench.run()?;
     Ok(())
 }
 
+fn load_mr() -> Corpus {
+    load_corpora(
+        "mr.txt",
+        &load_layout("layouts/whirl.txt"),
+        &Normalisation::default(),
+        &Directories::default(),
+        false,
+        Config::default().window_width(),
+    )
+}
+
 fn bench_analyse(bencher: Bencher, command: &str) {
-    let magic_rules = load_magic_rules("whirl.txt");
-    let corpus: String = fs::read_to_string("corpora/filtered/mr.txt").expect("corpus not loaded");
-    let layout_raw = load_layout("whirl.txt");
-    bencher.bench(|| analyze(corpus.clone(), layout_raw, command, &magic_rules));
+    let magic_rules = load_magic_rules("layouts/whirl.txt");
+    let corpus = load_mr();
+    let layout_raw = load_layout("layouts/whirl.txt");
+    let config = Config::default();
+    bencher.bench(|| analyze(&corpus, layout_raw, command, &magic_rules, &config));
 }
 
 fn bench_get_magic_rules(bencher: Bencher, magic_rules: usize) {
-    let corpus: String = fs::read_to_string("corpora/filtered/mr.txt").expect("corpus not loaded");
-    let layout_raw = load_layout("whirl.txt");
-    bencher.bench(|| get_magic_rules(&corpus, layout_raw, magic_rules));
+    let corpus = load_mr();
+    let layout_raw = load_layout("layouts/whirl.txt");
+    let config = Config::default();
+    bencher.bench(|| get_magic_rules(&corpus, &config, layout_raw, magic_rules));
 }
 
 fn bench_bigram_stats(bencher: Bencher, letters: &str) {
     let mut stats = Stats::default();
-    let finger_weights = load_finger_weights();
+    let config = Config::default();
 
     let (key1, key2) = load_two_keys(letters);
     bencher.bench(|| {
-        bigram_stats(&key1, &key2, "bench", &mut stats, &finger_weights);
+        bigram_stats(&key1, &key2, "bench", &mut stats, &config, 1);
     })
 }
+
+fn bench_skipgram_stats(bencher: Bencher, letters: &str) {
+    let mut stats = Stats::default();
+    let config = Config::default();
+
+    let (key1, _, key3) = load_three_keys(letters);
+    bencher.bench(|| {
+        skipgram_stats(&key1, &key3, "bench", &mut stats, &config, 1, 0);
+    })
+}
+
 fn bench_scissor(bencher: Bencher, letters: &str) {
     let (key1, key2) = load_two_keys(letters);
     bencher.bench(|| {
@@ -52,17 +90,8 @@ fn bench_scissor(bencher: Bencher, letters: &str) {
 
 fn bench_trigram_stats(bencher: Bencher, letters: &str) {
     let (key1, key2, key3) = load_three_keys(letters);
+    let thumbs = Config::default().thumbs;
     bencher.bench(|| {
-        trigram_stat(&key1, &key2, &key3);
+        trigram_stat(&key1, &key2, &key3, &thumbs);
     })
 }
-
-fn load_finger_weights() -> AHashMap<Finger, i64> {
-    AHashMap::from([
-        (Finger::Pinky, 66),
-        (Finger::Ring, 28),
-        (Finger::Middle, 21),
-        (Finger::Index, 18),
-        (Finger::Thumb, 50),
-    ])
-}
@@ -0,0 +1,704 @@
+//! Scoring settings that can be changed without recompiling, read from a TOML file.
+//! Anything left out of the file keeps its default value.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{stats::bigram_stats::ScissorDirection, Finger, Key, Stats, FINGER_NAMES, MAX_SKIP};
+
+/// All configurable parts of the scoring
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How same finger movements turn into `fspeed`
+    pub fspeed: FingerSpeed,
+    /// How much effort pressing each key takes, which makes up the heatmap
+    pub effort: Effort,
+    /// Which skipgrams are counted
+    pub skipgrams: Skipgrams,
+    /// Whether to count quadgram stats. Analysis is slower with them
+    pub quadgrams: bool,
+    /// How bad jumping between the top and bottom rows is
+    pub row_jumps: RowJumps,
+    /// Which trigram stats thumbs count towards
+    pub thumbs: Thumbs,
+    /// How the score is put together
+    pub scoring: Scoring,
+    /// What multi-objective generation optimises
+    pub pareto: Pareto,
+}
+
+impl Config {
+    /// Width of the corpus windows needed: the letters of the longest skipgram,
+    /// at least a trigram (or quadgram), plus one in front for magic rules
+    #[must_use]
+    pub fn window_width(&self) -> usize {
+        let ngram = if self.quadgrams { 4 } else { 3 };
+        usize::max(self.skipgrams.weights.len() + 2, ngram) + 1
+    }
+
+    /// Stats that are always 0 with this config, so weighting them would do nothing
+    #[must_use]
+    pub fn unused_stats(&self) -> Vec<String> {
+        let mut unused = Vec::new();
+        if !self.quadgrams {
+            unused.extend(["alt_chain", "roll_roll", "alt_red", "chained_sfb"].map(String::from));
+        }
+        // Only trigrams with thumbs left out are counted here
+        if self.thumbs.alt && self.thumbs.roll {
+            unused.push("thumb_stat".to_owned());
+        }
+        for distance in self.skipgrams.weights.len() + 1..=MAX_SKIP {
+            unused.extend(["sfs", "lss", "fss", "hss"].map(|stat| format!("{stat}@{distance}")));
+        }
+        unused
+    }
+}
+
+/// Skipgrams with up to `weights.len()` letters in between are counted.
+/// Each distance counts towards the score and fspeed by its weight, starting with
+/// one letter in between. Every distance is also reported separately
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Skipgrams {
+    /// Weight of each distance
+    pub weights: Vec<f64>,
+}
+
+impl Default for Skipgrams {
+    fn default() -> Self {
+        Skipgrams { weights: vec![1.0] }
+    }
+}
+
+/// The finger speed model.
+///
+/// Every time a finger has to press two different keys in a row, the penalty is
+/// `coefficient * finger weight * distance`, where the distance is the straight line
+/// between the two keys measured in key widths. The coefficient is `sfb` for bigrams and
+/// `sfs` for skipgrams, multiplied by the weight of the skipgram's distance in `Skipgrams`.
+/// Pressing the same key twice has no distance, so repeats cost `sfr * finger weight`.
+///
+/// The penalty of each finger is kept separately, and `fspeed` is their sum.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FingerSpeed {
+    /// Coefficient for same finger bigrams
+    pub sfb: f64,
+    /// Coefficient for same finger skipgrams
+    pub sfs: f64,
+    /// Coefficient for same finger repeats
+    pub sfr: f64,
+    /// How slow each finger is
+    pub weights: FingerWeights,
+    /// Replaced by `Skipgrams::weights`. Only read so old configs get told what to use instead
+    #[serde(skip_serializing)]
+    skip_decay: Option<f64>,
+}
+
+impl Default for FingerSpeed {
+    fn default() -> Self {
+        FingerSpeed {
+            sfb: 5.0,
+            sfs: 1.0,
+            sfr: 2.0,
+            weights: FingerWeights::default(),
+            skip_decay: None,
+        }
+    }
+}
+
+impl FingerSpeed {
+    /// Penalty for one same finger movement between two keys, with the given coefficient
+    #[must_use]
+    pub fn penalty(&self, key1: &Key, key2: &Key, coefficient: f64) -> f64 {
+        coefficient * self.weights.get(&key1.finger) * key1.distance(key2)
+    }
+}
+
+/// How slow each finger is. Higher is slower
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FingerWeights {
+    pub thumb: f64,
+    pub index: f64,
+    pub middle: f64,
+    pub ring: f64,
+    pub pinky: f64,
+}
+
+impl Default for FingerWeights {
+    fn default() -> Self {
+        FingerWeights {
+            thumb: 50.0,
+            index: 18.0,
+            middle: 21.0,
+            ring: 28.0,
+            pinky: 66.0,
+        }
+    }
+}
+
+impl FingerWeights {
+    /// Weight of a finger
+    #[must_use]
+    pub fn get(&self, finger: &Finger) -> f64 {
+        match finger {
+            Finger::Thumb => self.thumb,
+            Finger::Index => self.index,
+            Finger::Middle => self.middle,
+            Finger::Ring => self.ring,
+            Finger::Pinky => self.pinky,
+        }
+    }
+}
+
+/// Where the cost of each key for the heatmap comes from
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EffortModel {
+    /// Worked out from the position of each key, see `Effort`
+    Geometry,
+    /// A fixed cost for each of the 32 keys, tuned by hand for a 3x10 + 2 thumb keys layout.
+    /// The default, so heatmap scores stay the same unless geometry is asked for
+    #[default]
+    Static,
+}
+
+/// The fixed costs used by `EffortModel::Static`
+#[rustfmt::skip]
+const STATIC_EFFORT: [f64; 32] = [
+    12.0, 4.0, 3.0, 6.0, 7.0, 7.0, 6.0, 3.0, 4.0, 12.0,
+    3.0,  1.0, 0.0, 0.0, 6.0, 6.0, 0.0, 0.0, 1.0, 3.0,
+    8.0,  9.0, 8.0, 4.0, 9.0, 9.0, 4.0, 8.0, 9.0, 8.0,
+                    0.0,           0.0,
+];
+
+/// The effort model.
+///
+/// With the geometry model, the cost of a key is
+/// `finger strength * (1 + distance * distance from home + row reach)`.
+/// The distance from home is the straight line from the key the finger rests on,
+/// in key widths. Thumbs rest on their own keys. It is opt-in, with `model = "geometry"`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Effort {
+    /// Which model to use
+    pub model: EffortModel,
+    /// Cost of each key width away from the home position
+    pub distance: f64,
+    /// Extra cost of reaching each row: top, home, bottom, thumb
+    pub rows: [f64; 4],
+    /// How much harder each finger finds pressing a key. Higher is weaker
+    pub fingers: FingerStrength,
+}
+
+impl Default for Effort {
+    fn default() -> Self {
+        Effort {
+            model: EffortModel::default(),
+            distance: 3.0,
+            rows: [1.0, 0.0, 1.5, 0.0],
+            fingers: FingerStrength::default(),
+        }
+    }
+}
+
+impl Effort {
+    /// Cost of pressing the key at `position` in the layout
+    #[must_use]
+    pub fn cost(&self, position: usize, key: &Key) -> f64 {
+        match self.model {
+            EffortModel::Static => STATIC_EFFORT[position],
+            EffortModel::Geometry => {
+                self.fingers.get(&key.finger)
+                    * (1.0 + self.distance * home_distance(key) + self.rows[usize::from(key.row)])
+            }
+        }
+    }
+}
+
+/// Distance from the key the finger rests on, in key widths
+fn home_distance(key: &Key) -> f64 {
+    if key.finger == Finger::Thumb {
+        return 0.0;
+    }
+    // Fingers other than thumbs rest in the column of their finger index
+    let column = key.finger_index() as u8;
+    f64::from(key.column.abs_diff(column)).hypot(f64::from(key.row.abs_diff(1)))
+}
+
+/// How weak each finger is when pressing keys. Higher is weaker
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FingerStrength {
+    pub thumb: f64,
+    pub index: f64,
+    pub middle: f64,
+    pub ring: f64,
+    pub pinky: f64,
+}
+
+impl Default for FingerStrength {
+    fn default() -> Self {
+        FingerStrength {
+            thumb: 0.5,
+            index: 1.0,
+            middle: 1.0,
+            ring: 1.5,
+            pinky: 2.0,
+        }
+    }
+}
+
+impl FingerStrength {
+    /// Strength of a finger
+    #[must_use]
+    pub fn get(&self, finger: &Finger) -> f64 {
+        match finger {
+            Finger::Thumb => self.thumb,
+            Finger::Index => self.index,
+            Finger::Middle => self.middle,
+            Finger::Ring => self.ring,
+            Finger::Pinky => self.pinky,
+        }
+    }
+}
+
+/// What the score is measured against.
+///
+/// Every weight multiplies a stat, so with `Raw` the score grows with the size of the corpus.
+/// `Percent` and `PerMille` divide the score by the total characters, making each weight the
+/// cost of 1% (or 1‰) of characters being that stat, so scores from different corpora can be
+/// compared. The scale is a constant factor for any one corpus, so it doesn't change which
+/// layout is better. `generate` uses the scale from the config too, and simulated annealing
+/// takes its temperature from the spread of scores, so it searches the same way at any scale
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreScale {
+    /// Raw counts from the corpus
+    #[default]
+    Raw,
+    /// Per hundred characters
+    Percent,
+    /// Per thousand characters
+    PerMille,
+}
+
+impl ScoreScale {
+    /// Scale a raw score from a corpus of `chars` characters
+    #[must_use]
+    pub fn apply(self, score: f64, chars: u32) -> f64 {
+        match self {
+            ScoreScale::Raw => score,
+            ScoreScale::Percent => score * 100.0 / f64::from(chars),
+            ScoreScale::PerMille => score * 1000.0 / f64::from(chars),
+        }
+    }
+}
+
+/// Settings for the score
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scoring {
+    /// What the score is measured against
+    pub scale: ScoreScale,
+    /// Weight of each stat, by the names in `Stats::counters`. Weights in the config
+    /// replace the defaults one by one, and stats without a weight don't count
+    /// Totals like `fsb` can't be weighted along with the stats they add up, like
+    /// `fsb_short_up`, or the same ngrams would count twice
+    pub weights: BTreeMap<String, f64>,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            scale: ScoreScale::default(),
+            weights: default_weights(),
+        }
+    }
+}
+
+/// Weights used when the config doesn't change them. Full scissors used to count -500
+/// either way, and are now split by direction into weights that average to that. Half
+/// scissors have never counted: `hsb` and `hss` had weights (-100 and -20) that the score
+/// ignored, so they're left out to keep scores the same. The config can turn them on.
+/// Bad redirects, those with a thumb but no index finger, used to count as `red`, so they
+/// keep its weight
+fn default_weights() -> BTreeMap<String, f64> {
+    [
+        ("heatmap", -5.0),
+        ("column_pen", -10000.0),
+        ("fspeed", -30.0),
+        ("fsb_short_up", -750.0),
+        ("fsb_long_up", -250.0),
+        ("fss", -100.0),
+        ("lsb", -200.0),
+        ("lss", -40.0),
+        ("inroll", 100.0),
+        ("outroll", 40.0),
+        ("inthreeroll", 320.0),
+        ("outthreeroll", 160.0),
+        ("weak_red", -2000.0),
+        ("red", -300.0),
+        ("row_jump_pen", -100.0),
+        ("pinky_off", -1500.0),
+        ("sfb_red", -100.0),
+        ("bad_red", -300.0),
+        ("in_out_red", -200.0),
+    ]
+    .into_iter()
+    .map(|(name, weight)| (name.to_owned(), weight))
+    .collect()
+}
+
+/// Settings for multi-objective generation with the `pareto` command. Stats are named as in
+/// `Scoring::weights`, and compared by their raw counts
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pareto {
+    /// Stats where lower is better
+    pub minimise: Vec<String>,
+    /// Stats where higher is better
+    pub maximise: Vec<String>,
+    /// How many layouts are kept each generation
+    pub population: usize,
+}
+
+impl Default for Pareto {
+    fn default() -> Self {
+        Pareto {
+            minimise: ["sfb", "sfs", "red"].map(String::from).to_vec(),
+            maximise: ["inroll", "outroll"].map(String::from).to_vec(),
+            population: 40,
+        }
+    }
+}
+
+/// Whether trigrams with a thumb count as alternation and rolls (including redirects).
+/// Trigrams left out are counted as neither, but still count towards the total characters,
+/// so percentages stay comparable between layouts with and without letters on the thumbs
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thumbs {
+    pub alt: bool,
+    pub roll: bool,
+}
+
+impl Default for Thumbs {
+    fn default() -> Self {
+        Thumbs {
+            alt: true,
+            roll: true,
+        }
+    }
+}
+
+/// Penalty of a bigram on one hand jumping between the top and bottom rows.
+/// It depends on which finger reaches up: a shorter finger (by `Finger::length`)
+/// reaching up over a longer one is worse. Fingers next to each other have less room
+/// to stretch, so they're multiplied by `adjacent`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RowJumps {
+    /// Penalty when the shorter finger is on the top row
+    pub short_up: f64,
+    /// Penalty when the longer finger is on the top row
+    pub long_up: f64,
+    /// Multiplier for fingers next to each other
+    pub adjacent: f64,
+}
+
+impl Default for RowJumps {
+    fn default() -> Self {
+        RowJumps {
+            short_up: 3.0,
+            long_up: 1.0,
+            adjacent: 2.0,
+        }
+    }
+}
+
+impl RowJumps {
+    /// Penalty for one row jump
+    #[must_use]
+    pub fn penalty(&self, key1: &Key, key2: &Key) -> f64 {
+        let direction = match ScissorDirection::of(key1, key2) {
+            ScissorDirection::ShortUp => self.short_up,
+            ScissorDirection::LongUp => self.long_up,
+        };
+        if key1.finger_index().abs_diff(key2.finger_index()) == 1 {
+            direction * self.adjacent
+        } else {
+            direction
+        }
+    }
+}
+
+/// Load the config from a TOML file, or the defaults if there's no file
+pub fn load_config(path: Option<impl AsRef<Path>>) -> Config {
+    match path {
+        Some(path) => {
+            parse_config(&fs::read_to_string(path.as_ref()).expect("couldn't read config file"))
+                .unwrap_or_else(|error| {
+                    panic!("invalid config {}: {error}", path.as_ref().display())
+                })
+        }
+        None => Config::default(),
+    }
+}
+
+/// Parse a config from TOML
+pub fn parse_config(toml: &str) -> Result<Config, String> {
+    let mut config: Config = toml::from_str(toml).map_err(|error| error.to_string())?;
+    if let Some(decay) = config.fspeed.skip_decay {
+        return Err(format!(
+            "fspeed.skip_decay has been replaced by skipgrams.weights. \
+             Use weights = [1.0, {decay}] in [skipgrams] instead"
+        ));
+    }
+    if !(1..=MAX_SKIP).contains(&config.skipgrams.weights.len()) {
+        return Err(format!(
+            "skipgrams.weights needs between 1 and {MAX_SKIP} weights"
+        ));
+    }
+    let stats: Vec<String> = Stats::default()
+        .counters()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let unused = config.unused_stats();
+    let names = [
+        (
+            "scoring.weights",
+            config.scoring.weights.keys().collect::<Vec<_>>(),
+        ),
+        ("pareto.minimise", config.pareto.minimise.iter().collect()),
+        ("pareto.maximise", config.pareto.maximise.iter().collect()),
+    ];
+    for (section, names) in names {
+        for name in names {
+            if !stats.contains(name) {
+                return Err(format!("{section} has unknown stat {name}"));
+            }
+            if unused.contains(name) {
+                return Err(format!(
+                    "{section} has {name}, which is never counted with this config"
+                ));
+            }
+        }
+    }
+    if config.pareto.minimise.is_empty() && config.pareto.maximise.is_empty() {
+        return Err("pareto needs at least one stat to optimise".to_owned());
+    }
+    if config.pareto.population < 2 {
+        return Err("pareto.population needs to be at least 2".to_owned());
+    }
+    let mut weights = default_weights();
+    weights.append(&mut config.scoring.weights);
+    config.scoring.weights = weights;
+    let weighted = |name: &String| config.scoring.weights.get(name).is_some_and(|w| *w != 0.0);
+    for (total, parts) in overlapping_stats() {
+        if let Some(part) = parts.iter().find(|part| weighted(part)) {
+            if weighted(&total) {
+                return Err(format!(
+                    "scoring.weights has {total} and {part}, but {total} counts the same \
+                     ngrams as {}, so they would count twice. Set one of them to 0",
+                    parts.join(", ")
+                ));
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// Stats that add up other stats or count the same keys, with the stats they overlap
+fn overlapping_stats() -> Vec<(String, Vec<String>)> {
+    let fingers = FINGER_NAMES.map(str::to_lowercase);
+    let per_finger = |stat: &str, fingers: &[String]| {
+        fingers
+            .iter()
+            .map(|finger| format!("{stat}.{finger}"))
+            .collect()
+    };
+    let mut overlaps = vec![
+        (
+            "fsb".to_owned(),
+            vec!["fsb_short_up".to_owned(), "fsb_long_up".to_owned()],
+        ),
+        (
+            "hsb".to_owned(),
+            vec!["hsb_short_up".to_owned(), "hsb_long_up".to_owned()],
+        ),
+        (
+            "onehand".to_owned(),
+            [
+                "inthreeroll",
+                "outthreeroll",
+                "red",
+                "weak_red",
+                "pinky_off",
+                "sfb_red",
+                "bad_red",
+                "in_out_red",
+            ]
+            .map(String::from)
+            .to_vec(),
+        ),
+        (
+            "alt".to_owned(),
+            ["alt_clean", "alt_sfs", "alt_lateral", "alt_scissor"]
+                .map(String::from)
+                .to_vec(),
+        ),
+        ("row_jump".to_owned(), vec!["row_jump_pen".to_owned()]),
+        (
+            "thumb_usage".to_owned(),
+            ["row_usage.thumb", "finger_usage.lt", "finger_usage.rt"]
+                .map(String::from)
+                .to_vec(),
+        ),
+        ("sfb".to_owned(), per_finger("finger_sfb", &fingers)),
+        ("sfs".to_owned(), per_finger("finger_sfs", &fingers)),
+        ("fspeed".to_owned(), per_finger("finger_fspeed", &fingers)),
+        (
+            "hand_usage.left".to_owned(),
+            per_finger("finger_usage", &fingers[..5]),
+        ),
+        (
+            "hand_usage.right".to_owned(),
+            per_finger("finger_usage", &fingers[5..]),
+        ),
+    ];
+    for stat in ["sfs", "lss", "fss", "hss"] {
+        let distances = (1..=MAX_SKIP).map(|distance| format!("{stat}@{distance}"));
+        overlaps.push((stat.to_owned(), distances.collect()));
+    }
+    overlaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_config, Config, Effort, EffortModel, ScoreScale, STATIC_EFFORT};
+    use crate::{load_layout, stats::layout_raw_to_table};
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(parse_config("").unwrap(), Config::default());
+        let config = parse_config("[fspeed]\nsfb = 3.0\n[fspeed.weights]\npinky = 70.0").unwrap();
+        assert_eq!(config.fspeed.sfb, 3.0);
+        assert_eq!(config.fspeed.sfs, 1.0);
+        assert_eq!(config.fspeed.weights.pinky, 70.0);
+        assert_eq!(config.fspeed.weights.index, 18.0);
+        let config = parse_config("[thumbs]\nalt = false").unwrap();
+        assert!(!config.thumbs.alt);
+        assert!(config.thumbs.roll);
+    }
+
+    #[test]
+    fn test_unknown_config_key() {
+        assert!(parse_config("[fspeed]\nsbf = 3.0").is_err());
+        assert!(parse_config("[speed]").is_err());
+        assert!(parse_config("[effort]\nmodel = \"flat\"").is_err());
+        assert!(parse_config("[skipgrams]\nweights = []").is_err());
+        let error = parse_config("[fspeed]\nskip_decay = 0.5").unwrap_err();
+        assert!(error.contains("weights = [1.0, 0.5]"));
+    }
+
+    #[test]
+    fn test_weights() {
+        let config = parse_config("[scoring.weights]\nhsb = -100.0\nred = 0.0").unwrap();
+        assert_eq!(config.scoring.weights["hsb"], -100.0);
+        assert_eq!(config.scoring.weights["red"], 0.0);
+        // Weights left out keep their defaults
+        assert_eq!(config.scoring.weights["fspeed"], -30.0);
+        // Bad redirects used to be plain redirects, and still score like them
+        let defaults = Config::default().scoring.weights;
+        assert_eq!(defaults["bad_red"], defaults["red"]);
+        assert!(parse_config("[scoring.weights]\n\"finger_sfb.lp\" = -10.0").is_ok());
+
+        assert!(parse_config("[scoring.weights]\nsbf = -100.0").is_err());
+        assert!(parse_config("[scoring.weights]\nalt_chain = 10.0").is_err());
+        assert!(parse_config("quadgrams = true\n[scoring.weights]\nalt_chain = 10.0").is_ok());
+        assert!(parse_config("[scoring.weights]\n\"sfs@2\" = -10.0").is_err());
+        assert!(parse_config("[scoring.weights]\nthumb_stat = -10.0").is_err());
+        let thumbs = "[thumbs]\nalt = false\n[scoring.weights]\nthumb_stat = -10.0";
+        assert!(parse_config(thumbs).is_ok());
+
+        // Totals can't be weighted along with the stats they add up
+        assert!(parse_config("[scoring.weights]\nfsb = -500.0").is_err());
+        let fsb = "[scoring.weights]\nfsb = -500.0\nfsb_short_up = 0.0\nfsb_long_up = 0.0";
+        assert!(parse_config(fsb).is_ok());
+        assert!(parse_config("[scoring.weights]\nhsb = -1.0\nhsb_long_up = -1.0").is_err());
+        assert!(parse_config("[scoring.weights]\nonehand = -10.0").is_err());
+        assert!(parse_config("[scoring.weights]\nalt = 10.0\nalt_sfs = -10.0").is_err());
+        assert!(parse_config("[scoring.weights]\nrow_jump = -100.0").is_err());
+        let row_jump = "[scoring.weights]\nrow_jump = -100.0\nrow_jump_pen = 0.0";
+        assert!(parse_config(row_jump).is_ok());
+        let thumbs = "[scoring.weights]\nthumb_usage = -1.0\n\"row_usage.thumb\" = -1.0";
+        assert!(parse_config(thumbs).is_err());
+        let thumbs = "[scoring.weights]\nthumb_usage = -1.0\n\"finger_usage.rt\" = -1.0";
+        assert!(parse_config(thumbs).is_err());
+        let skipgrams = "[skipgrams]\nweights = [1.0, 0.5]\n[scoring.weights]\n\"lss@2\" = -1.0";
+        assert!(parse_config(skipgrams).is_err());
+        assert!(parse_config("[scoring.weights]\nhss = -20.0").is_ok());
+    }
+
+    #[test]
+    fn test_pareto() {
+        let config = parse_config("[pareto]\nminimise = [\"sfb\"]\nmaximise = []").unwrap();
+        assert_eq!(config.pareto.minimise, ["sfb"]);
+        assert_eq!(config.pareto.population, 40);
+        assert!(parse_config("[pareto]\nminimise = [\"sbf\"]").is_err());
+        assert!(parse_config("[pareto]\nmaximise = [\"alt_chain\"]").is_err());
+        assert!(parse_config("[pareto]\nminimise = []\nmaximise = []").is_err());
+        assert!(parse_config("[pareto]\npopulation = 1").is_err());
+    }
+
+    #[test]
+    fn test_score_scale() {
+        let config = parse_config("[scoring]\nscale = \"permille\"").unwrap();
+        assert_eq!(config.scoring.scale, ScoreScale::PerMille);
+        assert_eq!(ScoreScale::Raw.apply(-500.0, 200), -500.0);
+        assert_eq!(ScoreScale::Percent.apply(-500.0, 200), -250.0);
+        assert_eq!(ScoreScale::PerMille.apply(-500.0, 200), -2500.0);
+    }
+
+    #[test]
+    fn test_window_width() {
+        assert_eq!(Config::default().window_width(), 4);
+        let config = parse_config("[skipgrams]\nweights = [1.0, 0.5, 0.25]").unwrap();
+        assert_eq!(config.window_width(), 6);
+        let config = parse_config("quadgrams = true").unwrap();
+        assert_eq!(config.window_width(), 5);
+    }
+
+    #[test]
+    fn test_effort() {
+        let layout = load_layout("layouts/whirl.txt");
+        let table = layout_raw_to_table(&layout);
+        let costs = |effort: &Effort| -> Vec<f64> {
+            (0..32)
+                .map(|i| effort.cost(i, &table[&layout[i]]))
+                .collect()
+        };
+
+        assert_eq!(costs(&Effort::default()), STATIC_EFFORT);
+
+        let geometry = costs(&Effort {
+            model: EffortModel::Geometry,
+            ..Default::default()
+        });
+        // Index home is cheapest for fingers, thumbs are cheaper still
+        assert_eq!(geometry[13], 1.0);
+        assert_eq!(geometry[30], 0.5);
+        // Reaching up with the pinky costs more than the index
+        assert!(geometry[0] > geometry[3]);
+        // Lateral keys cost more than home
+        assert!(geometry[14] > geometry[13]);
+        assert_eq!(
+            geometry[0..10],
+            geometry[0..10].iter().rev().copied().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,648 @@
+pub mod import;
+
+use ahash::AHashMap;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::Directories;
+
+/// What to do with newlines in the raw corpus
+#[derive(ValueEnum, Serialize, Default, Hash, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Newlines {
+    /// Drop newlines, joining the lines together
+    #[default]
+    Remove,
+    /// Treat newlines as spaces
+    Space,
+}
+
+/// How uppercase letters are written to the corpus
+#[derive(ValueEnum, Serialize, Default, Hash, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Shift {
+    /// Replace uppercase letters with `*` followed by the lowercase letter
+    #[default]
+    Star,
+    /// Replace uppercase letters with the lowercase letter
+    Lower,
+    /// Leave uppercase letters alone
+    Keep,
+}
+
+/// What to do with digits and symbols
+#[derive(ValueEnum, Serialize, Default, Hash, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Handling {
+    /// Leave them in. They are still dropped if they're not on the layout
+    #[default]
+    Keep,
+    /// Remove them
+    Drop,
+    /// Replace them with a space
+    Space,
+}
+
+/// Settings for turning a raw corpus into a filtered one
+#[derive(clap::Args, Serialize, Default, Hash, PartialEq, Eq, Debug, Clone)]
+pub struct Normalisation {
+    /// How to handle newlines
+    #[arg(long, value_enum, default_value_t)]
+    pub newlines: Newlines,
+
+    /// How to encode uppercase letters
+    #[arg(long, value_enum, default_value_t)]
+    pub shift: Shift,
+
+    /// Fold accented letters and typographic punctuation into ASCII (é => e, ’ => ')
+    #[arg(long, action)]
+    pub fold_unicode: bool,
+
+    /// How to handle digits
+    #[arg(long, value_enum, default_value_t)]
+    pub digits: Handling,
+
+    /// How to handle symbols (anything that isn't a letter, digit or whitespace)
+    #[arg(long, value_enum, default_value_t)]
+    pub symbols: Handling,
+
+    /// Collapse runs of whitespace into one space
+    #[arg(long, action)]
+    pub collapse_whitespace: bool,
+}
+
+/// Runs the raw text through the normalisation pipeline, keeping only letters from the layout
+pub fn normalise(raw: &str, settings: &Normalisation, layout_raw: &[char; 32]) -> String {
+    let mut corpus = apply_normalisation(raw, settings);
+    corpus.retain(|ch| layout_raw.contains(&ch));
+    corpus
+}
+
+/// Runs the raw text through the normalisation pipeline, without dropping letters that aren't
+/// on the layout
+fn apply_normalisation(raw: &str, settings: &Normalisation) -> String {
+    let mut text = String::with_capacity(raw.len());
+    for ch in raw.chars() {
+        let ch = match ch {
+            '\n' | '\r' if settings.newlines == Newlines::Remove => continue,
+            '\n' | '\r' => ' ',
+            _ => ch,
+        };
+        match fold(ch).filter(|_| settings.fold_unicode) {
+            Some(folded) => text.push_str(folded),
+            None => text.push(ch),
+        }
+    }
+
+    let mut corpus = String::with_capacity(text.len());
+    // A `_` already in the text isn't whitespace, so isn't collapsed
+    let mut after_whitespace = false;
+    for ch in text.chars() {
+        let handling = if ch.is_ascii_digit() {
+            settings.digits
+        } else if !ch.is_alphanumeric() && !ch.is_whitespace() {
+            settings.symbols
+        } else {
+            Handling::Keep
+        };
+        let ch = match handling {
+            Handling::Keep => ch,
+            Handling::Drop => continue,
+            Handling::Space => ' ',
+        };
+        if ch.is_whitespace() {
+            if settings.collapse_whitespace && after_whitespace {
+                continue;
+            }
+            if ch == ' ' || settings.collapse_whitespace {
+                corpus.push('_');
+                after_whitespace = true;
+                continue;
+            }
+        }
+        after_whitespace = false;
+        if ch.is_uppercase() && settings.shift != Shift::Keep {
+            if settings.shift == Shift::Star {
+                corpus.push('*');
+            }
+            corpus.extend(ch.to_lowercase());
+            continue;
+        }
+        corpus.push(ch);
+    }
+    corpus
+}
+
+/// Characters left after normalisation that aren't on the layout, so are dropped by the filter,
+/// most common first. Also returns how many characters there were before they were dropped
+pub fn dropped_chars(
+    raw: &str,
+    settings: &Normalisation,
+    layout_raw: &[char; 32],
+) -> (Vec<(char, u64)>, u64) {
+    let mut dropped: AHashMap<char, u64> = AHashMap::default();
+    let mut total = 0;
+    for ch in apply_normalisation(raw, settings).chars() {
+        total += 1;
+        if !layout_raw.contains(&ch) {
+            *dropped.entry(ch).or_insert(0) += 1;
+        }
+    }
+    let mut dropped: Vec<(char, u64)> = dropped.into_iter().collect();
+    dropped.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    (dropped, total)
+}
+
+/// Folds accented letters and typographic punctuation into their closest ASCII form
+fn fold(ch: char) -> Option<&'static str> {
+    Some(match ch {
+        'à'..='å' => "a",
+        'À'..='Å' => "A",
+        'ç' => "c",
+        'Ç' => "C",
+        'è'..='ë' => "e",
+        'È'..='Ë' => "E",
+        'ì'..='ï' => "i",
+        'Ì'..='Ï' => "I",
+        'ñ' => "n",
+        'Ñ' => "N",
+        'ò'..='ö' | 'ø' => "o",
+        'Ò'..='Ö' | 'Ø' => "O",
+        'ù'..='ü' => "u",
+        'Ù'..='Ü' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' => "Y",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ß' => "ss",
+        '‘' | '’' | 'ʼ' => "'",
+        '“' | '”' => "\"",
+        '–' | '—' => "-",
+        '…' => "...",
+        '\u{a0}' => " ",
+        _ => return None,
+    })
+}
+
+/// Sorted, deduplicated characters of a layout
+fn alphabet(layout_raw: &[char; 32]) -> String {
+    let mut alphabet = layout_raw.to_vec();
+    alphabet.sort_unstable();
+    alphabet.dedup();
+    alphabet.into_iter().collect()
+}
+
+/// Get the key the filtered corpus is cached under.
+/// Changes whenever the settings or the characters on the layout change
+pub fn cache_key(settings: &Normalisation, layout_raw: &[char; 32]) -> u64 {
+    stable_hash(format!("{settings:?} {}", alphabet(layout_raw)).as_bytes())
+}
+
+/// First line of a filtered corpus. Describes what the corpus was built from,
+/// so it can be rebuilt if any of it changes
+pub fn cache_header(raw: &str, settings: &Normalisation, layout_raw: &[char; 32]) -> String {
+    format!(
+        "chogalyzer-corpus raw={:016x} settings={settings:?} alphabet={}",
+        stable_hash(raw.as_bytes()),
+        alphabet(layout_raw)
+    )
+}
+
+/// 64 bit FNV-1a hash. Unlike `DefaultHasher` it's the same on every Rust version, so caches
+/// aren't rebuilt after upgrading
+fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Read a raw corpus. `-` reads stdin, and a directory reads every file in it
+pub fn read_raw_corpus(corpus_name: &str, dirs: &Directories) -> String {
+    if corpus_name == "-" {
+        let mut raw = String::new();
+        io::stdin()
+            .read_to_string(&mut raw)
+            .expect("error reading corpus from stdin");
+        return raw;
+    }
+    let path = dirs.corpus_path(corpus_name);
+    eprintln!("{}", path.display());
+    if path.is_dir() {
+        let mut files = Vec::new();
+        collect_files(&path, &mut files);
+        files.sort();
+        // Files that aren't text are skipped
+        files
+            .iter()
+            .filter_map(|file| fs::read_to_string(file).ok())
+            .collect::<Vec<String>>()
+            .join("\n")
+    } else {
+        fs::read_to_string(path).expect("error reading corpus")
+    }
+}
+
+/// Recursively find every file in a directory, skipping hidden ones such as `.git`
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir).expect("error reading corpus directory");
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Filter corpus with the normalisation settings and only letters from the layout,
+/// then write it to the cache with its header
+fn filter_corpus(
+    raw: &str,
+    header: &str,
+    layout_raw: &[char; 32],
+    settings: &Normalisation,
+    cache_path: &Path,
+) -> String {
+    let corpus = normalise(raw, settings, layout_raw);
+    if let Some(dir) = cache_path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let mut write_file = File::create(cache_path).expect("couldn't write corpus");
+    let _ = write_file.write_all(format!("{header}\n{corpus}").as_bytes());
+    corpus
+}
+
+/// Load corpus, and filter it with the layout if it has not been previously
+/// filtered from the same raw text, with the same settings and layout characters.
+/// `rebuild` filters it again regardless. Corpora from stdin are never cached
+pub fn load_corpus(
+    corpus_name: &str,
+    layout: &[char; 32],
+    settings: &Normalisation,
+    dirs: &Directories,
+    rebuild: bool,
+) -> String {
+    let raw = read_raw_corpus(corpus_name, dirs);
+    if corpus_name == "-" {
+        return normalise(&raw, settings, layout);
+    }
+    let header = cache_header(&raw, settings, layout);
+    // Corpora given as paths still get a flat name in the cache
+    let cache_name: String = corpus_name
+        .chars()
+        .map(|ch| {
+            if matches!(ch, '/' | '\\' | ':') {
+                '_'
+            } else {
+                ch
+            }
+        })
+        .collect();
+    let cache_path = dirs
+        .cache_dir
+        .join(format!("{cache_name}-{:016x}", cache_key(settings, layout)));
+    if !rebuild {
+        if let Ok(cached) = fs::read_to_string(&cache_path) {
+            match cached.split_once('\n') {
+                Some((cached_header, corpus)) if cached_header == header => {
+                    return corpus.to_string();
+                }
+                _ => eprintln!("corpus is out of date, now rebuilding"),
+            }
+        } else {
+            eprintln!("couldn't find corpus, now loading");
+        }
+    }
+    filter_corpus(&raw, &header, layout, settings, &cache_path)
+}
+
+/// A corpus as counts of sliding windows of characters.
+/// Every character in the text ends exactly one window, so the window holds it,
+/// the bigram ending in it and the skipgrams ending in it.
+/// The first character is only there so magic rules can be applied to the rest of the window
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Corpus {
+    /// Each window and how often it occurs, most common first. All windows are the same width
+    pub windows: Vec<(Vec<char>, u32)>,
+}
+impl Corpus {
+    /// Count the windows of a filtered corpus. `width` includes the character for magic rules
+    pub fn from_text(text: &str, width: usize) -> Corpus {
+        let mut counts: AHashMap<Vec<char>, u32> = AHashMap::default();
+        let mut window = vec!['_'; width];
+        for letter in text.chars() {
+            window.rotate_left(1);
+            window[width - 1] = letter;
+            // Only allocate for windows that haven't been seen yet
+            match counts.get_mut(window.as_slice()) {
+                Some(count) => *count += 1,
+                None => {
+                    counts.insert(window.clone(), 1);
+                }
+            }
+        }
+        Corpus::from_counts(counts)
+    }
+
+    pub(crate) fn from_counts(counts: AHashMap<Vec<char>, u32>) -> Corpus {
+        let mut windows: Vec<(Vec<char>, u32)> = counts.into_iter().collect();
+        windows.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Corpus { windows }
+    }
+
+    /// Width of each window
+    pub fn width(&self) -> usize {
+        self.windows.first().map_or(0, |(window, _)| window.len())
+    }
+
+    /// Total count of characters
+    pub fn chars(&self) -> u64 {
+        self.windows.iter().map(|(_, freq)| u64::from(*freq)).sum()
+    }
+
+    /// Count the n-grams made of the characters at `positions` in each window, most common first.
+    /// Positions count back from the end of the window, and magic rules aren't applied.
+    /// `[0]` gives monograms, `[1, 0]` bigrams, `[2, 0]` skipgrams and `[2, 1, 0]` trigrams
+    pub fn ngrams<const N: usize>(&self, positions: [usize; N]) -> Vec<([char; N], u64)> {
+        let mut counts: AHashMap<[char; N], u64> = AHashMap::default();
+        for (window, freq) in &self.windows {
+            let last = window.len() - 1;
+            *counts
+                .entry(positions.map(|i| window[last - i]))
+                .or_insert(0) += u64::from(*freq);
+        }
+        let mut ngrams: Vec<([char; N], u64)> = counts.into_iter().collect();
+        ngrams.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ngrams
+    }
+
+    /// Blend corpora together by weight. Each corpus is normalised first so a large corpus
+    /// doesn't drown out a small one. The result is scaled to the size of all corpora combined
+    pub fn blend(corpora: &[(Corpus, f64)]) -> Corpus {
+        #![allow(clippy::cast_precision_loss)]
+        let total_weight: f64 = corpora.iter().map(|(_, weight)| weight).sum();
+        let total_chars: u64 = corpora.iter().map(|(corpus, _)| corpus.chars()).sum();
+        let mut counts: AHashMap<Vec<char>, u32> = AHashMap::default();
+        for (corpus, weight) in corpora {
+            let scale = weight / total_weight * total_chars as f64 / corpus.chars() as f64;
+            for (window, freq) in &corpus.windows {
+                let freq = (f64::from(*freq) * scale).round() as u32;
+                if freq > 0 {
+                    *counts.entry(window.clone()).or_insert(0) += freq;
+                }
+            }
+        }
+        Corpus::from_counts(counts)
+    }
+}
+
+/// Split a corpus list such as `mr.txt:0.6,code:0.3,chat:0.1` into names and weights.
+/// Corpora without a weight get a weight of 1. Errors if a weight isn't a positive number
+pub fn parse_corpora(spec: &str) -> Result<Vec<(&str, f64)>, String> {
+    spec.split(',')
+        .filter(|corpus| !corpus.is_empty())
+        .map(|corpus| match corpus.rsplit_once(':') {
+            Some((name, weight)) => match weight.parse::<f64>() {
+                Ok(weight) if weight.is_finite() && weight > 0.0 => Ok((name, weight)),
+                Ok(_) => Err(format!(
+                    "corpus weights need to be positive numbers, but got {corpus}"
+                )),
+                // The colon was part of the path
+                Err(_) => Ok((corpus, 1.0)),
+            },
+            None => Ok((corpus, 1.0)),
+        })
+        .collect()
+}
+
+/// Load every corpus in a weighted list and blend them together, with windows of `width`.
+/// Corpora can be text, or n-grams from other analyzers
+pub fn load_corpora(
+    spec: &str,
+    layout: &[char; 32],
+    settings: &Normalisation,
+    dirs: &Directories,
+    rebuild: bool,
+    width: usize,
+) -> Corpus {
+    let corpora: Vec<(Corpus, f64)> = parse_corpora(spec)
+        .unwrap_or_else(|error| panic!("{error}"))
+        .into_iter()
+        .map(|(name, weight)| {
+            let path = dirs.corpus_path(name);
+            if import::is_ngram_corpus(&path) {
+                return (
+                    import::load_ngram_corpus(&path, layout, settings, width),
+                    weight,
+                );
+            }
+            let text = load_corpus(name, layout, settings, dirs, rebuild);
+            (Corpus::from_text(&text, width), weight)
+        })
+        .collect();
+    assert!(!corpora.is_empty(), "no corpus given");
+    if corpora.len() == 1 {
+        return corpora.into_iter().next().expect("no corpus given").0;
+    }
+    Corpus::blend(&corpora)
+}
+
+/// Characters dropped from every text corpus in a weighted list, and how many characters there
+/// were before they were dropped. N-gram corpora and stdin can't be read again, so are skipped
+pub fn corpora_dropped_chars(
+    spec: &str,
+    layout: &[char; 32],
+    settings: &Normalisation,
+    dirs: &Directories,
+) -> (Vec<(char, u64)>, u64) {
+    let mut dropped: AHashMap<char, u64> = AHashMap::default();
+    let mut total = 0;
+    for (name, _) in parse_corpora(spec).unwrap_or_else(|error| panic!("{error}")) {
+        if name == "-" || import::is_ngram_corpus(&dirs.corpus_path(name)) {
+            continue;
+        }
+        let (corpus_dropped, corpus_total) =
+            dropped_chars(&read_raw_corpus(name, dirs), settings, layout);
+        for (ch, count) in corpus_dropped {
+            *dropped.entry(ch).or_insert(0) += count;
+        }
+        total += corpus_total;
+    }
+    let mut dropped: Vec<(char, u64)> = dropped.into_iter().collect();
+    dropped.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    (dropped, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::{
+            cache_header, cache_key, dropped_chars, normalise, parse_corpora, read_raw_corpus,
+            stable_hash, Corpus, Handling, Newlines, Normalisation, Shift,
+        },
+        load_layout, Directories,
+    };
+
+    #[test]
+    fn test_default_normalisation() {
+        let layout = load_layout("layouts/whirl.txt");
+        let corpus = normalise("Hi there,\n\nWorld 42!", &Normalisation::default(), &layout);
+        assert_eq!(corpus, "*hi_there,*world_");
+    }
+
+    #[test]
+    fn test_normalisation_options() {
+        let layout = load_layout("layouts/whirl.txt");
+        let settings = Normalisation {
+            newlines: Newlines::Space,
+            shift: Shift::Lower,
+            fold_unicode: true,
+            digits: Handling::Drop,
+            symbols: Handling::Space,
+            collapse_whitespace: true,
+        };
+        let corpus = normalise("Café’s  au\nlait 42 - hi", &settings, &layout);
+        assert_eq!(corpus, "cafe_s_au_lait_hi");
+
+        // Underscores in the text aren't collapsed with the whitespace after them
+        let settings = Normalisation {
+            collapse_whitespace: true,
+            ..Normalisation::default()
+        };
+        let corpus = normalise("snake_ case  here", &settings, &layout);
+        assert_eq!(corpus, "snake__case_here");
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let whirl = load_layout("layouts/whirl.txt");
+        let qwerty = load_layout("layouts/qwerty.txt");
+        let mut with_dash = qwerty;
+        with_dash[27] = '-';
+        let settings = Normalisation::default();
+        let folded = Normalisation {
+            fold_unicode: true,
+            ..Normalisation::default()
+        };
+        assert_eq!(cache_key(&settings, &whirl), cache_key(&settings, &whirl));
+        assert_ne!(cache_key(&settings, &whirl), cache_key(&folded, &whirl));
+        // Same characters in a different order share a cache
+        assert_eq!(cache_key(&settings, &whirl), cache_key(&settings, &qwerty));
+        assert_ne!(
+            cache_key(&settings, &qwerty),
+            cache_key(&settings, &with_dash)
+        );
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        // Known FNV-1a values, so cache names don't change between builds
+        assert_eq!(stable_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_cache_header() {
+        let whirl = load_layout("layouts/whirl.txt");
+        let settings = Normalisation::default();
+        let header = cache_header("the raw text", &settings, &whirl);
+        assert_eq!(header, cache_header("the raw text", &settings, &whirl));
+        assert_ne!(header, cache_header("the raw text!", &settings, &whirl));
+        assert!(header.ends_with("alphabet='*,.;_abcdefghijklmnopqrstuvwxyz"));
+        assert!(!header.contains('\n'));
+    }
+
+    #[test]
+    fn test_read_raw_corpus() {
+        let dirs = Directories::default();
+        let by_name = read_raw_corpus("e200.txt", &dirs);
+        assert_eq!(by_name, read_raw_corpus("corpora/raw/e200.txt", &dirs));
+        // A directory is every file in it, in order
+        let by_dir = read_raw_corpus("corpora/raw", &dirs);
+        assert!(by_dir.starts_with(&by_name));
+        assert!(by_dir.len() > by_name.len());
+    }
+
+    #[test]
+    fn test_windows() {
+        let corpus = Corpus::from_text("abab", 4);
+        assert_eq!(
+            corpus.windows,
+            vec![
+                (vec!['_', '_', '_', 'a'], 1),
+                (vec!['_', '_', 'a', 'b'], 1),
+                (vec!['_', 'a', 'b', 'a'], 1),
+                (vec!['a', 'b', 'a', 'b'], 1)
+            ]
+        );
+        assert_eq!(corpus.chars(), 4);
+        assert_eq!(corpus.width(), 4);
+        let wide = Corpus::from_text("abab", 6);
+        assert_eq!(wide.windows[3], (vec!['_', '_', 'a', 'b', 'a', 'b'], 1));
+    }
+
+    #[test]
+    fn test_blend() {
+        let small = Corpus::from_text("ab", 4);
+        let large = Corpus::from_text(&"c".repeat(30), 4);
+        let blend = Corpus::blend(&[(small, 1.0), (large, 1.0)]);
+        let count = |window: [char; 4]| {
+            blend
+                .windows
+                .iter()
+                .find(|(w, _)| *w == window)
+                .map_or(0, |(_, freq)| *freq)
+        };
+        // Both corpora make up half of the 32 characters, even though one is 15 times the size
+        assert_eq!(
+            count(['_', '_', '_', 'a']) + count(['_', '_', 'a', 'b']),
+            16
+        );
+        assert_eq!(count(['c', 'c', 'c', 'c']), 14);
+    }
+
+    #[test]
+    fn test_parse_corpora() {
+        assert_eq!(
+            parse_corpora("mr.txt:0.6,code:0.3,chat"),
+            Ok(vec![("mr.txt", 0.6), ("code", 0.3), ("chat", 1.0)])
+        );
+        assert_eq!(
+            parse_corpora("C:/corpus.txt"),
+            Ok(vec![("C:/corpus.txt", 1.0)])
+        );
+        // Weights that would empty the blend or drop a corpus are named
+        for bad in ["code:0", "code:-1", "code:NaN", "code:inf"] {
+            let error = parse_corpora(&format!("mr.txt,{bad}")).unwrap_err();
+            assert!(error.contains(bad), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_ngrams() {
+        let corpus = Corpus::from_text("abab", 4);
+        assert_eq!(corpus.ngrams([0]), vec![(['a'], 2), (['b'], 2)]);
+        assert_eq!(corpus.ngrams([1, 0])[0], (['a', 'b'], 2));
+        assert!(corpus.ngrams([2, 0]).contains(&(['a', 'a'], 1)));
+        assert_eq!(corpus.ngrams([2, 1, 0]).len(), 4);
+    }
+
+    #[test]
+    fn test_dropped_chars() {
+        let layout = load_layout("layouts/whirl.txt");
+        let (dropped, total) = dropped_chars("Hi! 42 ü!", &Normalisation::default(), &layout);
+        assert_eq!(dropped, vec![('!', 2), ('2', 1), ('4', 1), ('ü', 1)]);
+        assert_eq!(total, 10);
+    }
+}
@@ -0,0 +1,192 @@
+use ahash::AHashMap;
+use serde_json::{Map, Value};
+use std::{fs, path::Path};
+
+use crate::corpus::{normalise, Corpus, Normalisation};
+
+/// Counts are scaled to this total when a file only has relative frequencies
+const FREQUENCY_SCALE: f64 = 10_000_000.0;
+
+/// Whether a corpus should be read as precomputed n-grams rather than text.
+/// That's any `.json` file, or a directory with a `trigrams.json` in it (as cmini does)
+pub fn is_ngram_corpus(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+        || (path.is_dir() && path.join("trigrams.json").exists())
+}
+
+/// Load a corpus of precomputed n-grams from another analyzer, with windows of `width`
+pub fn load_ngram_corpus(
+    path: &Path,
+    layout_raw: &[char; 32],
+    settings: &Normalisation,
+    width: usize,
+) -> Corpus {
+    let file = if path.is_dir() {
+        path.join("trigrams.json")
+    } else {
+        path.to_path_buf()
+    };
+    let json = fs::read_to_string(&file).expect("error reading corpus");
+    parse_ngram_json(&json, layout_raw, settings, width)
+        .unwrap_or_else(|error| panic!("couldn't import {}: {error}", file.display()))
+}
+
+/// Turn an n-gram JSON file into a corpus.
+/// Every window can be built from a trigram, so only the trigrams are read.
+/// Monograms, bigrams and skipgrams follow from them.
+/// Windows wider than a trigram are padded at the start with spaces, which are never on a layout,
+/// so anything longer than a trigram isn't counted.
+/// Trigrams are put through the normalisation settings. Any that grow, such as uppercase
+/// letters gaining a `*`, keep as much of the end as fits in the window. Any with fewer than
+/// three characters left on the layout are dropped, and how many is reported
+pub fn parse_ngram_json(
+    json: &str,
+    layout_raw: &[char; 32],
+    settings: &Normalisation,
+    width: usize,
+) -> Result<Corpus, String> {
+    let value: Value = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    let trigrams = find_trigrams(&value).ok_or("no trigrams found")?;
+
+    let total: f64 = trigrams.iter().map(|(_, freq)| freq).sum();
+    // Some analyzers store relative frequencies instead of counts
+    let scale = if trigrams.iter().all(|(_, freq)| freq.fract() == 0.0) {
+        1.0
+    } else {
+        FREQUENCY_SCALE / total
+    };
+
+    let mut counts: AHashMap<Vec<char>, u32> = AHashMap::default();
+    let mut dropped = 0;
+    for (trigram, freq) in trigrams {
+        let trigram: Vec<char> = normalise(&trigram, settings, layout_raw).chars().collect();
+        let freq = (freq * scale).round() as u32;
+        if freq == 0 {
+            continue;
+        }
+        if trigram.len() < 3 {
+            dropped += 1;
+            continue;
+        }
+        // The window ends on the last letter, so only the end of a long trigram is kept
+        // There's no character before the trigram, so no magic rule can apply to it
+        let kept = &trigram[trigram.len().saturating_sub(width)..];
+        let mut window = vec![' '; width - kept.len()];
+        window.extend_from_slice(kept);
+        *counts.entry(window).or_insert(0) += freq;
+    }
+    if dropped > 0 {
+        eprintln!("dropped {dropped} trigrams that don't fit on the layout");
+    }
+    if counts.is_empty() {
+        return Err("no trigrams fit on the layout".to_string());
+    }
+    Ok(Corpus::from_counts(counts))
+}
+
+/// Find the trigram table, whatever analyzer the file came from
+fn find_trigrams(value: &Value) -> Option<Vec<(String, f64)>> {
+    let object = value.as_object()?;
+    // genkey, oxeylyzer and similar keep each table under its own key
+    if let Some((_, table)) = object
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("trigrams"))
+    {
+        return table_entries(table);
+    }
+    // cmini style files are a table on their own
+    if object.keys().all(|key| key.chars().count() == 3) {
+        return table_entries(value);
+    }
+    None
+}
+
+/// Read a table stored either as `{"the": 10}` or as `[["the", 10]]`
+fn table_entries(table: &Value) -> Option<Vec<(String, f64)>> {
+    match table {
+        Value::Object(map) => object_entries(map),
+        Value::Array(pairs) => pairs
+            .iter()
+            .map(|pair| match pair.as_array()?.as_slice() {
+                [Value::String(ngram), freq] => Some((ngram.clone(), freq.as_f64()?)),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+fn object_entries(map: &Map<String, Value>) -> Option<Vec<(String, f64)>> {
+    map.iter()
+        .map(|(ngram, freq)| Some((ngram.clone(), freq.as_f64()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::{import::parse_ngram_json, Corpus, Normalisation},
+        load_layout,
+    };
+
+    fn import(json: &str) -> Corpus {
+        let layout = load_layout("layouts/whirl.txt");
+        parse_ngram_json(json, &layout, &Normalisation::default(), 4).unwrap()
+    }
+
+    #[test]
+    fn test_genkey_counts() {
+        let corpus = import(r#"{"Letters": {"t": 3}, "Trigrams": {"the": 2, "he ": 1}}"#);
+        assert_eq!(
+            corpus.windows,
+            vec![(vec![' ', 't', 'h', 'e'], 2), (vec![' ', 'h', 'e', '_'], 1)]
+        );
+    }
+
+    #[test]
+    fn test_oxeylyzer_frequencies() {
+        let corpus = import(r#"{"characters": {}, "trigrams": {"the": 0.75, "and": 0.25}}"#);
+        assert_eq!(corpus.chars(), 10_000_000);
+        assert_eq!(corpus.windows[0], (vec![' ', 't', 'h', 'e'], 7_500_000));
+    }
+
+    #[test]
+    fn test_cmini_table() {
+        // Trigrams that don't fit on the layout are dropped
+        let corpus = import(r#"{"the": 5, "t@e": 5}"#);
+        assert_eq!(corpus.windows, vec![(vec![' ', 't', 'h', 'e'], 5)]);
+        // Tables can also be lists of pairs
+        let corpus = import(r#"{"trigrams": [["the", 5]]}"#);
+        assert_eq!(corpus.chars(), 5);
+        // Wider windows are padded
+        let layout = load_layout("layouts/whirl.txt");
+        let corpus =
+            parse_ngram_json(r#"{"the": 5}"#, &layout, &Normalisation::default(), 6).unwrap();
+        assert_eq!(
+            corpus.windows,
+            vec![(vec![' ', ' ', ' ', 't', 'h', 'e'], 5)]
+        );
+    }
+
+    #[test]
+    fn test_shifted_trigrams() {
+        // `The` becomes `*the`, which still fits in the window
+        let corpus = import(r#"{"The": 3, "the": 2}"#);
+        assert_eq!(
+            corpus.windows,
+            vec![(vec!['*', 't', 'h', 'e'], 3), (vec![' ', 't', 'h', 'e'], 2)]
+        );
+        // Only the end of a trigram that no longer fits is kept
+        let corpus = import(r#"{"ThE": 1}"#);
+        assert_eq!(corpus.windows, vec![(vec!['t', 'h', '*', 'e'], 1)]);
+    }
+
+    #[test]
+    fn test_no_trigrams() {
+        let layout = load_layout("layouts/whirl.txt");
+        let settings = Normalisation::default();
+        assert!(parse_ngram_json(r#"{"bigrams": {"th": 1}}"#, &layout, &settings, 4).is_err());
+        assert!(parse_ngram_json("not json", &layout, &settings, 4).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Explains the difference between two layouts with the same keys: which keys moved, and
+//! which bigrams and trigrams count as something else now
+
+use crate::{
+    config::Config,
+    corpus::Corpus,
+    stats::{
+        apply_magic,
+        bigram_stats::{bigram_stat, bigram_stats},
+        layout_raw_to_table, score,
+        trigram_stats::{trigram_stat, trigram_stats},
+    },
+    Key, Stats, MAX_SKIP,
+};
+use ahash::AHashMap;
+use serde::Serialize;
+
+/// A bigram or trigram that counts as something else on the new layout
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NgramChange {
+    /// The letters as typed, before magic
+    pub ngram: String,
+    /// What it counted as on the old layout
+    pub old: String,
+    /// What it counts as on the new layout
+    pub new: String,
+    pub freq: u32,
+    /// How much this ngram changed the score by, in the scale of the config. Only the bigram
+    /// and trigram stats it adds to count, not per-key stats like heatmap
+    pub score_delta: f64,
+}
+
+/// A layout with its magic rules
+pub type LayoutRules<'a> = (&'a [char; 32], &'a AHashMap<char, char>);
+
+/// Keys that are somewhere else on `new`, with their old and new positions.
+/// Errors with the keys added and removed if the layouts don't have the same keys
+pub fn moved_keys(old: &[char; 32], new: &[char; 32]) -> Result<Vec<(char, usize, usize)>, String> {
+    let missing = |from: &[char; 32], to: &[char; 32]| -> String {
+        from.iter().filter(|key| !to.contains(key)).collect()
+    };
+    let (added, removed) = (missing(new, old), missing(old, new));
+    if !added.is_empty() || !removed.is_empty() {
+        return Err(format!(
+            "layouts to diff need the same keys, but the new layout adds {added:?} and removes \
+             {removed:?}"
+        ));
+    }
+    Ok(old
+        .iter()
+        .enumerate()
+        .filter_map(|(i, key)| {
+            let j = new.iter().position(|other| other == key)?;
+            (i != j).then_some((*key, i, j))
+        })
+        .collect())
+}
+
+/// Every bigram and trigram whose category changed between the layouts, biggest change
+/// to the score first
+#[must_use]
+pub fn changed_ngrams(
+    corpus: &Corpus,
+    old: LayoutRules,
+    new: LayoutRules,
+    config: &Config,
+) -> Vec<NgramChange> {
+    // Magic can type the same letters differently on each layout, so ngrams are grouped by
+    // what was typed and what each layout presses
+    let mut bigrams: AHashMap<[[char; 2]; 3], u32> = AHashMap::default();
+    let mut trigrams: AHashMap<[[char; 3]; 3], u32> = AHashMap::default();
+    let mut chars = 0;
+    let (mut old_buffer, mut new_buffer) = ([' '; MAX_SKIP + 3], [' '; MAX_SKIP + 3]);
+    for (window, freq) in &corpus.windows {
+        let old_window = &mut old_buffer[..window.len()];
+        let new_window = &mut new_buffer[..window.len()];
+        old_window.copy_from_slice(window);
+        new_window.copy_from_slice(window);
+        for (window, (layout, magic_rules)) in [(&mut *old_window, old), (&mut *new_window, new)] {
+            if layout.contains(&'*') {
+                apply_magic(window, layout, magic_rules);
+            }
+        }
+        let last = window.len() - 1;
+        let trigram = |window: &[char]| [window[last - 2], window[last - 1], window[last]];
+        let bigram = |window: &[char]| [window[last - 1], window[last]];
+        chars += freq;
+        *bigrams
+            .entry([bigram(window), bigram(old_window), bigram(new_window)])
+            .or_insert(0) += freq;
+        *trigrams
+            .entry([trigram(window), trigram(old_window), trigram(new_window)])
+            .or_insert(0) += freq;
+    }
+
+    let old_table = layout_raw_to_table(old.0);
+    let new_table = layout_raw_to_table(new.0);
+    let mut changes = Vec::new();
+    for ([typed, old_ngram, new_ngram], freq) in bigrams {
+        // Windows imported from trigrams are padded with letters that aren't on the layout
+        let (Some(old_keys), Some(new_keys)) =
+            (keys(&old_table, old_ngram), keys(&new_table, new_ngram))
+        else {
+            continue;
+        };
+        let (old_stat, old_score) = bigram_effect(old_keys, config, freq);
+        let (new_stat, new_score) = bigram_effect(new_keys, config, freq);
+        if old_stat != new_stat {
+            changes.push(change(
+                &typed,
+                old_stat,
+                new_stat,
+                freq,
+                new_score - old_score,
+            ));
+        }
+    }
+    for ([typed, old_ngram, new_ngram], freq) in trigrams {
+        let (Some(old_keys), Some(new_keys)) =
+            (keys(&old_table, old_ngram), keys(&new_table, new_ngram))
+        else {
+            continue;
+        };
+        let (old_stat, old_score) = trigram_effect(old_keys, config, freq);
+        let (new_stat, new_score) = trigram_effect(new_keys, config, freq);
+        if old_stat != new_stat {
+            changes.push(change(
+                &typed,
+                old_stat,
+                new_stat,
+                freq,
+                new_score - old_score,
+            ));
+        }
+    }
+    for change in &mut changes {
+        change.score_delta = config.scoring.scale.apply(change.score_delta, chars);
+    }
+    changes.sort_by(|a, b| b.score_delta.abs().total_cmp(&a.score_delta.abs()));
+    changes
+}
+
+/// Keys of an ngram, if they're all on the layout
+fn keys<const N: usize>(table: &AHashMap<char, Key>, ngram: [char; N]) -> Option<[&Key; N]> {
+    let keys: Vec<&Key> = ngram
+        .iter()
+        .map(|letter| table.get(letter))
+        .collect::<Option<_>>()?;
+    keys.try_into().ok()
+}
+
+/// A change to an ngram typed as `typed`
+fn change(typed: &[char], old: String, new: String, freq: u32, score_delta: f64) -> NgramChange {
+    NgramChange {
+        ngram: typed.iter().collect(),
+        old,
+        new,
+        freq,
+        score_delta,
+    }
+}
+
+/// Category of a bigram, and how much it adds to the raw score
+fn bigram_effect([key1, key2]: [&Key; 2], config: &Config, freq: u32) -> (String, f64) {
+    let mut stats = Stats::default();
+    bigram_stats(key1, key2, "diff", &mut stats, config, freq);
+    let stat = format!("{:?}", bigram_stat(key1, key2));
+    (stat, score(&stats, &config.scoring.weights))
+}
+
+/// Category of a trigram, and how much it adds to the raw score
+fn trigram_effect([key1, key2, key3]: [&Key; 3], config: &Config, freq: u32) -> (String, f64) {
+    let (stats, _) = trigram_stats(
+        key1,
+        key2,
+        key3,
+        "diff",
+        Stats::default(),
+        &config.thumbs,
+        freq,
+    );
+    let stat = format!("{:?}", trigram_stat(key1, key2, key3, &config.thumbs));
+    (stat, score(&stats, &config.scoring.weights))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{changed_ngrams, moved_keys};
+    use crate::{config::Config, corpus::Corpus, load_layout};
+    use ahash::AHashMap;
+
+    #[test]
+    fn test_moved_keys() {
+        let old = load_layout("layouts/whirl.txt");
+        assert_eq!(moved_keys(&old, &old), Ok(Vec::new()));
+        let mut new = old;
+        new.swap(0, 31);
+        assert_eq!(
+            moved_keys(&old, &new),
+            Ok(vec![(old[0], 0, 31), (old[31], 31, 0)])
+        );
+        // Keys that aren't on both layouts are listed rather than panicking
+        let semicolon = old.iter().position(|&key| key == ';').unwrap();
+        new[semicolon] = '-';
+        let error = moved_keys(&old, &new).unwrap_err();
+        assert!(error.contains("\"-\"") && error.contains("\";\""));
+    }
+
+    #[test]
+    fn test_changed_ngrams() {
+        let config = Config::default();
+        let old = load_layout("layouts/whirl.txt");
+        let corpus = Corpus::from_text("ed", config.window_width());
+        let no_magic = AHashMap::default();
+        assert!(changed_ngrams(&corpus, (&old, &no_magic), (&old, &no_magic), &config).is_empty());
+
+        // `d` moves above `e`, on the same finger
+        let mut new = old;
+        let (d, u) = (
+            old.iter().position(|&key| key == 'd'),
+            old.iter().position(|&key| key == 'u'),
+        );
+        new.swap(d.unwrap(), u.unwrap());
+        let changes = changed_ngrams(&corpus, (&old, &no_magic), (&new, &no_magic), &config);
+        let ed = changes.iter().find(|change| change.ngram == "ed").unwrap();
+        assert_eq!((ed.old.as_str(), ed.new.as_str()), ("None", "SFB"));
+        assert_eq!(ed.freq, 1);
+        assert!(ed.score_delta < 0.0);
+    }
+}
@@ -1,8 +1,11 @@
 // This should be multiple files I think. Pretty disgusting overall
+pub mod pareto;
 
 use crate::{
+    config::Config,
+    corpus::Corpus,
     stats::{self, analyze, bigram_stats, layout_raw_to_table},
-    Algorithm, Finger, Layout, Stats,
+    Algorithm, Layout, Stats,
 };
 use ahash::{AHashMap, AHashSet};
 use indicatif::MultiProgress;
@@ -19,7 +22,8 @@ const THREADS: usize = 12;
 #[must_use]
 pub fn generate_threads(
     layout_raw: [char; 32],
-    corpus: &String,
+    corpus: &Corpus,
+    config: &Config,
     max_iterations: u64,
     magic_rules: usize,
     cooling_rate: f64,
@@ -37,6 +41,7 @@ pub fn generate_threads(
                         generate(
                             layout_raw,
                             corpus,
+                            config,
                             max_iterations,
                             &bars,
                             magic_rules,
@@ -58,9 +63,11 @@ pub fn generate_threads(
 
 /// Generates one layout, supporting multiple algorithms
 // This is pretty disgusting. Oh well.
+#[allow(clippy::too_many_arguments)]
 fn generate(
     layout_raw: [char; 32],
-    corpus: &String,
+    corpus: &Corpus,
+    config: &Config,
     max_iterations: u64,
     multibars: &MultiProgress,
     magic_rules: usize,
@@ -68,12 +75,12 @@ fn generate(
     algorithm: Algorithm,
     runid: usize,
 ) -> Layout {
-    let mut layout = randomise_layout(layout_raw, corpus.clone(), magic_rules);
+    let mut layout = randomise_layout(layout_raw, corpus, config, magic_rules);
     let mut iterations = 0;
     let bar = ProgressBar::new(max_iterations);
     multibars.add(bar.clone());
     // specifically for sim annealing
-    let mut temperature = get_temperature(&mut layout, corpus);
+    let mut temperature = get_temperature(&mut layout, corpus, config);
     let start = Instant::now();
     let hill_switch_temp = 10.0;
     while iterations < max_iterations {
@@ -81,15 +88,15 @@ fn generate(
         let new_layout = if algorithm == Algorithm::HillClimbing
             || (algorithm == Algorithm::Hybrid && temperature <= hill_switch_temp)
         {
-            let find_best_swap = find_best_swap(layout.layout, corpus, magic_rules);
+            let find_best_swap = find_best_swap(layout.layout, corpus, config, magic_rules);
             if find_best_swap.1 {
                 return find_best_swap.0;
             }
             find_best_swap.0
         } else if algorithm == Algorithm::RandomLayout {
-            randomise_layout(layout_raw, corpus.clone(), magic_rules)
+            randomise_layout(layout_raw, corpus, config, magic_rules)
         } else {
-            attempt_swap(layout.clone(), corpus, magic_rules)
+            attempt_swap(layout.clone(), corpus, config, magic_rules)
         };
         layout = if algorithm == Algorithm::HillClimbing
             || (algorithm == Algorithm::SimAnnealing
@@ -133,12 +140,17 @@ fn generate(
 }
 
 /// Creates a random layout to start generating a layout from
-fn randomise_layout(layout_raw: [char; 32], corpus: String, magic_rule_number: usize) -> Layout {
+fn randomise_layout(
+    layout_raw: [char; 32],
+    corpus: &Corpus,
+    config: &Config,
+    magic_rule_number: usize,
+) -> Layout {
     let mut rng = rand::rng();
     let mut new_layout_raw = layout_raw;
     new_layout_raw.shuffle(&mut rng);
-    let magic_rules = get_magic_rules(&corpus, new_layout_raw, magic_rule_number);
-    let stats = analyze(corpus, new_layout_raw, "generate", &magic_rules);
+    let magic_rules = get_magic_rules(corpus, config, new_layout_raw, magic_rule_number);
+    let stats = analyze(corpus, new_layout_raw, "generate", &magic_rules, config);
     Layout {
         layout: new_layout_raw,
         magic: magic_rules,
@@ -149,12 +161,13 @@ fn randomise_layout(layout_raw: [char; 32], corpus: String, magic_rule_number: u
 /// For the hill climbing algorithm. Finds the best swap possible
 fn find_best_swap(
     layout_raw: [char; 32],
-    corpus: &String,
+    corpus: &Corpus,
+    config: &Config,
     magic_rules_number: usize,
 ) -> (Layout, bool) {
     let old_layout = layout_raw;
-    let old_magic = get_magic_rules(corpus, layout_raw, magic_rules_number);
-    let old_stats = analyze(corpus.to_string(), layout_raw, "generate", &old_magic);
+    let old_magic = get_magic_rules(corpus, config, layout_raw, magic_rules_number);
+    let old_stats = analyze(corpus, layout_raw, "generate", &old_magic, config);
     let mut best_layout = Layout {
         layout: old_layout,
         magic: old_magic,
@@ -165,8 +178,8 @@ fn find_best_swap(
         for letter2 in (letter1 + 1)..layout_raw.len() {
             let mut new_layout = old_layout;
             new_layout.swap(letter1, letter2);
-            let new_magic_rules = get_magic_rules(corpus, new_layout, magic_rules_number);
-            let new_stats = analyze(corpus.to_string(), new_layout, "generate", &new_magic_rules);
+            let new_magic_rules = get_magic_rules(corpus, config, new_layout, magic_rules_number);
+            let new_stats = analyze(corpus, new_layout, "generate", &new_magic_rules, config);
             if new_stats.score > best_layout.stats.score {
                 has_changed = true;
                 best_layout = Layout {
@@ -183,48 +196,81 @@ fn find_best_swap(
     (best_layout, false)
 }
 
+/// Improves a layout from where it is, keeping the swaps that raise the score.
+/// Its magic rules stay as they are
+#[must_use]
+pub fn improve_layout(
+    mut layout: Layout,
+    corpus: &Corpus,
+    config: &Config,
+    max_iterations: u64,
+) -> Layout {
+    let bar = ProgressBar::new(max_iterations);
+    for _ in 0..max_iterations {
+        let mut new_layout = layout.clone();
+        new_layout.layout = random_swap(new_layout.layout);
+        new_layout.stats = stats::analyze(
+            corpus,
+            new_layout.layout,
+            "generate",
+            &new_layout.magic,
+            config,
+        );
+        if new_layout.stats.score > layout.stats.score {
+            layout = new_layout;
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+    layout
+}
+
 /// Get the temperature to start out from with the simulated annealing
-fn get_temperature(layout: &mut Layout, corpus: &String) -> f64 {
+fn get_temperature(layout: &mut Layout, corpus: &Corpus, config: &Config) -> f64 {
     let mut score_array: [f64; 10] = Default::default();
     for score in &mut score_array {
         let mut rng = rand::rng();
         let letter1 = rng.random_range(0..layout.layout.len());
         let letter2 = rng.random_range(0..layout.layout.len());
         layout.layout.swap(letter1, letter2);
-        layout.stats = stats::analyze(corpus.to_string(), layout.layout, "generate", &layout.magic);
+        layout.stats = stats::analyze(corpus, layout.layout, "generate", &layout.magic, config);
         *score = layout.stats.score;
     }
     standard_deviation(&score_array.clone())
 }
 
 /// Do a swap and analyse it.
-pub fn attempt_swap(old_layout: Layout, corpus: &String, magic_rules: usize) -> Layout {
-    let mut rng = rand::rng();
+pub fn attempt_swap(
+    old_layout: Layout,
+    corpus: &Corpus,
+    config: &Config,
+    magic_rules: usize,
+) -> Layout {
     let mut new_layout = old_layout;
-    // swap letters or column
-    if rng.random_range(0..10) > 3 {
-        new_layout
-            .layout
-            .swap(rng.random_range(0..32), rng.random_range(0..32));
-    } else {
-        new_layout.layout = column_swap(
-            new_layout.layout,
-            rng.random_range(1..10),
-            rng.random_range(1..10),
-        );
-    }
-
-    new_layout.magic = get_magic_rules(&corpus.to_string(), new_layout.layout, magic_rules);
+    new_layout.layout = random_swap(new_layout.layout);
+    new_layout.magic = get_magic_rules(corpus, config, new_layout.layout, magic_rules);
 
     new_layout.stats = stats::analyze(
-        corpus.to_string(),
+        corpus,
         new_layout.layout,
         "generate",
         &new_layout.magic,
+        config,
     );
     new_layout
 }
 
+/// Swap two random letters or columns
+fn random_swap(mut layout: [char; 32]) -> [char; 32] {
+    let mut rng = rand::rng();
+    if rng.random_range(0..10) > 3 {
+        layout.swap(rng.random_range(0..32), rng.random_range(0..32));
+        layout
+    } else {
+        column_swap(layout, rng.random_range(1..10), rng.random_range(1..10))
+    }
+}
+
 /// Out of all the layouts generated by different threads; find the best one
 fn get_best_layout(layouts: &[Layout; THREADS]) -> Layout {
     let mut best_score = layouts[0].stats.score;
@@ -262,7 +308,7 @@ fn annealing_func(old: f64, new: f64, temperature: f64) -> bool {
 }
 
 /// Swaps two columns on a layout
-fn column_swap(mut layout: [char; 32], col1: usize, col2: usize) -> [char; 32] {
+pub fn column_swap(mut layout: [char; 32], col1: usize, col2: usize) -> [char; 32] {
     layout.swap(col1, col2);
     layout.swap(col1 + 10, col2 + 10);
     layout.swap(col1 + 20, col2 + 20);
@@ -271,22 +317,16 @@ fn column_swap(mut layout: [char; 32], col1: usize, col2: usize) -> [char; 32] {
 
 /// Generate magic rules
 pub fn get_magic_rules(
-    corpus: &str,
+    corpus: &Corpus,
+    config: &Config,
     layout_letters: [char; 32],
     magic_rules: usize,
 ) -> AHashMap<char, char> {
     let layout = layout_raw_to_table(&layout_letters);
-    let mut previous_letter = '_';
     let mut stats: Stats = Stats::default();
-    let finger_weights: AHashMap<Finger, i64> = AHashMap::from([
-        (Finger::Pinky, 66),
-        (Finger::Ring, 28),
-        (Finger::Middle, 21),
-        (Finger::Index, 18),
-        (Finger::Thumb, 50),
-    ]);
 
-    for letter in corpus.chars() {
+    for (window, freq) in &corpus.windows {
+        let (previous_letter, letter) = (window[window.len() - 2], window[window.len() - 1]);
         let key = &layout[&letter];
         let previous_key = &layout[&previous_letter];
         let bigram = bigram_stats::bigram_stats(
@@ -294,15 +334,15 @@ pub fn get_magic_rules(
             key,
             "get_bad_bigrams",
             &mut stats,
-            &finger_weights,
+            config,
+            *freq,
         );
         if bigram.1 > 0 {
             *stats
                 .bad_bigrams
                 .entry([previous_letter, letter])
-                .or_insert(0) += bigram.1 as u32;
+                .or_insert(0) += bigram.1 as u32 * freq;
         }
-        previous_letter = letter;
     }
     let mut sorted_vec: Vec<([char; 2], u32)> = stats.bad_bigrams.into_iter().collect();
 
@@ -0,0 +1,322 @@
+//! Multi-objective generation with NSGA-II. Rather than one score, layouts are compared on
+//! several stats at once, and the result is the Pareto front: every layout that no other
+//! layout beats on all of the stats
+
+use super::{get_magic_rules, THREADS};
+use crate::{config::Config, corpus::Corpus, stats::analyze, Layout};
+use indicatif::ProgressBar;
+use rand::prelude::*;
+use rand::seq::SliceRandom;
+use std::{cmp::Ordering, thread};
+
+/// Generates layouts for `generations` generations, and returns the ones on the Pareto front
+#[must_use]
+pub fn generate_pareto(
+    layout_raw: [char; 32],
+    corpus: &Corpus,
+    config: &Config,
+    generations: u64,
+    magic_rules: usize,
+) -> Vec<Layout> {
+    let size = config.pareto.population;
+    let mut rng = rand::rng();
+    let starting_layouts = (0..size)
+        .map(|_| {
+            let mut layout = layout_raw;
+            layout.shuffle(&mut rng);
+            layout
+        })
+        .collect();
+    let mut population = evaluate(starting_layouts, corpus, config, magic_rules);
+    let bar = ProgressBar::new(generations);
+    for _ in 0..generations {
+        let ranks = rank(&objectives(&population, config));
+        let mut tournament = || {
+            let (a, b) = (rng.random_range(0..size), rng.random_range(0..size));
+            if better(ranks[a], ranks[b]) {
+                a
+            } else {
+                b
+            }
+        };
+        let children = (0..size)
+            .map(|_| {
+                let (parent1, parent2) = (tournament(), tournament());
+                let mut rng = rand::rng();
+                let mut cuts = [rng.random_range(0..32), rng.random_range(0..32)];
+                cuts.sort_unstable();
+                let mut child = order_crossover(
+                    &population[parent1].layout,
+                    &population[parent2].layout,
+                    cuts[0],
+                    cuts[1],
+                );
+                child.swap(rng.random_range(0..32), rng.random_range(0..32));
+                child
+            })
+            .collect();
+        population.extend(evaluate(children, corpus, config, magic_rules));
+        population = select(population, size, config);
+        bar.inc(1);
+    }
+    bar.finish();
+
+    let ranks = rank(&objectives(&population, config));
+    let mut front: Vec<Layout> = Vec::new();
+    for (layout, (rank, _)) in population.into_iter().zip(ranks) {
+        if rank == 0 && !front.iter().any(|other| other.layout == layout.layout) {
+            front.push(layout);
+        }
+    }
+    front
+}
+
+/// Generates magic rules for and analyses each layout, spread over threads
+fn evaluate(
+    layouts: Vec<[char; 32]>,
+    corpus: &Corpus,
+    config: &Config,
+    magic_rules: usize,
+) -> Vec<Layout> {
+    let chunk_size = layouts.len().div_ceil(THREADS).max(1);
+    thread::scope(|s| {
+        let handles: Vec<_> = layouts
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|&layout| {
+                            let magic = get_magic_rules(corpus, config, layout, magic_rules);
+                            let stats = analyze(corpus, layout, "generate", &magic, config);
+                            Layout {
+                                layout,
+                                magic,
+                                stats,
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("generation thread panicked"))
+            .collect()
+    })
+}
+
+/// Keeps the best `size` layouts, by front then by how spread out they are
+fn select(population: Vec<Layout>, size: usize, config: &Config) -> Vec<Layout> {
+    let ranks = rank(&objectives(&population, config));
+    let mut order: Vec<usize> = (0..population.len()).collect();
+    order.sort_by(|&a, &b| compare(ranks[a], ranks[b]));
+    order.truncate(size);
+    let mut population: Vec<Option<Layout>> = population.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .map(|i| population[i].take().expect("each layout is selected once"))
+        .collect()
+}
+
+/// The objectives of each layout, all turned into ones to be minimised
+pub fn objectives(population: &[Layout], config: &Config) -> Vec<Vec<f64>> {
+    population
+        .iter()
+        .map(|layout| {
+            let value = |name: &String| {
+                layout
+                    .stats
+                    .counter(name)
+                    .expect("objectives are checked when loading the config") as f64
+            };
+            let minimise = config.pareto.minimise.iter().map(value);
+            let maximise = config.pareto.maximise.iter().map(|name| -value(name));
+            minimise.chain(maximise).collect()
+        })
+        .collect()
+}
+
+/// Whether `a` is at least as good as `b` on every objective, and better on one
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a <= b) && a.iter().zip(b).any(|(a, b)| a < b)
+}
+
+/// Ranks each solution by its front (0 is the Pareto front) and its crowding distance.
+/// Solutions are better on a lower front, then with a higher crowding distance
+fn rank(objectives: &[Vec<f64>]) -> Vec<(usize, f64)> {
+    let mut ranks = vec![(0, 0.0); objectives.len()];
+    for (front_rank, front) in non_dominated_sort(objectives).into_iter().enumerate() {
+        for (i, distance) in front.iter().zip(crowding_distance(objectives, &front)) {
+            ranks[*i] = (front_rank, distance);
+        }
+    }
+    ranks
+}
+
+/// Order of two ranks from `rank`, best first
+fn compare(a: (usize, f64), b: (usize, f64)) -> Ordering {
+    a.0.cmp(&b.0).then(b.1.total_cmp(&a.1))
+}
+
+/// Whether a rank from `rank` is better than another
+fn better(a: (usize, f64), b: (usize, f64)) -> bool {
+    compare(a, b) == Ordering::Less
+}
+
+/// Splits solutions into fronts, where no solution is dominated by one in its own or a later
+/// front. Returns the indices in each front, best first
+fn non_dominated_sort(objectives: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut dominated_by = vec![0; n];
+    let mut dominates_list: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for a in 0..n {
+        for b in 0..n {
+            if dominates(&objectives[a], &objectives[b]) {
+                dominates_list[a].push(b);
+            } else if dominates(&objectives[b], &objectives[a]) {
+                dominated_by[a] += 1;
+            }
+        }
+    }
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..n).filter(|&i| dominated_by[i] == 0).collect();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for &a in &front {
+            for &b in &dominates_list[a] {
+                dominated_by[b] -= 1;
+                if dominated_by[b] == 0 {
+                    next.push(b);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next;
+    }
+    fronts
+}
+
+/// How far each solution in a front is from its neighbours, summed over the objectives
+/// scaled to the range of the front. Solutions at the edges are infinitely far
+fn crowding_distance(objectives: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.0; front.len()];
+    let Some(first) = front.first() else {
+        return distances;
+    };
+    for (objective, _) in objectives[*first].iter().enumerate() {
+        let values: Vec<f64> = front.iter().map(|&i| objectives[i][objective]).collect();
+        let value = |i: usize| values[i];
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|&a, &b| value(a).total_cmp(&value(b)));
+        let (min, max) = (value(order[0]), value(order[order.len() - 1]));
+        distances[order[0]] = f64::INFINITY;
+        distances[order[order.len() - 1]] = f64::INFINITY;
+        if max > min {
+            for window in order.windows(3) {
+                distances[window[1]] += (value(window[2]) - value(window[0])) / (max - min);
+            }
+        }
+    }
+    distances
+}
+
+/// Order crossover: the child keeps `parent1` between `start` and `end`, and the rest of the
+/// keys in the order they come in `parent2`
+fn order_crossover(
+    parent1: &[char; 32],
+    parent2: &[char; 32],
+    start: usize,
+    end: usize,
+) -> [char; 32] {
+    let kept = &parent1[start..end];
+    let mut rest = parent2.iter().filter(|key| !kept.contains(key));
+    let mut child = *parent1;
+    for (i, key) in child.iter_mut().enumerate() {
+        if !(start..end).contains(&i) {
+            *key = *rest.next().expect("parents have the same keys");
+        }
+    }
+    child
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        better, crowding_distance, dominates, generate_pareto, non_dominated_sort, objectives,
+        order_crossover, rank,
+    };
+    use crate::{config::Config, corpus::Corpus, load_layout};
+
+    #[test]
+    fn test_dominates() {
+        assert!(dominates(&[1.0, 2.0], &[1.0, 3.0]));
+        assert!(!dominates(&[1.0, 3.0], &[1.0, 3.0]));
+        assert!(!dominates(&[0.0, 4.0], &[1.0, 3.0]));
+    }
+
+    #[test]
+    fn test_non_dominated_sort() {
+        let objectives = vec![
+            vec![1.0, 4.0],
+            vec![2.0, 2.0],
+            vec![4.0, 1.0],
+            vec![3.0, 3.0],
+            vec![4.0, 4.0],
+        ];
+        let fronts = non_dominated_sort(&objectives);
+        assert_eq!(fronts, vec![vec![0, 1, 2], vec![3], vec![4]]);
+    }
+
+    #[test]
+    fn test_crowding_distance() {
+        let objectives = vec![vec![1.0, 4.0], vec![2.0, 2.0], vec![4.0, 1.0]];
+        let distances = crowding_distance(&objectives, &[0, 1, 2]);
+        assert_eq!(distances[0], f64::INFINITY);
+        assert_eq!(distances[2], f64::INFINITY);
+        assert_eq!(distances[1], 1.0 + 1.0);
+
+        // Further out solutions are better within a front
+        let ranks = rank(&objectives);
+        assert!(ranks.iter().all(|(front, _)| *front == 0));
+        assert!(better(ranks[0], ranks[1]));
+        assert!(!better(ranks[1], ranks[2]));
+    }
+
+    #[test]
+    fn test_generate_pareto() {
+        let mut config = Config::default();
+        config.pareto.population = 4;
+        let layout = load_layout("layouts/whirl.txt");
+        let corpus = Corpus::from_text("the_quick_brown_fox", config.window_width());
+        let front = generate_pareto(layout, &corpus, &config, 2, 0);
+        assert!(!front.is_empty());
+        let objectives = objectives(&front, &config);
+        for a in &objectives {
+            for b in &objectives {
+                assert!(!dominates(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_order_crossover() {
+        let parent1 = load_layout("layouts/whirl.txt");
+        let mut parent2 = parent1;
+        parent2.reverse();
+        let child = order_crossover(&parent1, &parent2, 5, 15);
+        assert_eq!(child[5..15], parent1[5..15]);
+        let mut sorted_child = child;
+        let mut sorted_parent = parent1;
+        sorted_child.sort_unstable();
+        sorted_parent.sort_unstable();
+        assert_eq!(sorted_child, sorted_parent);
+        // Keys outside the cut come in the order of the other parent
+        let rest: Vec<char> = parent2
+            .iter()
+            .filter(|key| !parent1[5..15].contains(key))
+            .copied()
+            .collect();
+        assert_eq!(child[..5], rest[..5]);
+    }
+}
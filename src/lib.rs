@@ -1,15 +1,23 @@
+pub mod config;
+pub mod corpus;
+pub mod diff;
 pub mod generation;
 pub mod output;
+pub mod repl;
 pub mod stats;
 
 use ahash::AHashMap;
 use clap::Parser;
 use std::{
-    fs::{self, File},
-    io::Write,
+    fs, io,
+    path::{Path, PathBuf},
 };
 
-use crate::stats::layout_raw_to_table;
+use crate::{
+    corpus::{load_corpus, Normalisation},
+    output::{NgramFilter, OutputFormat},
+    stats::layout_raw_to_table,
+};
 
 /// Contains all information about a key's position
 #[derive(PartialEq, Debug, Clone)]
@@ -17,9 +25,42 @@ pub struct Key {
     pub hand: u8,
     pub finger: Finger,
     pub row: u8,
+    /// Column from the left edge, lateral columns included. Thumb keys sit below the lateral columns
+    pub column: u8,
     pub lateral: bool,
 }
 
+impl Key {
+    /// Index of the finger pressing the key, from the left pinky (0) to the right pinky (9)
+    #[must_use]
+    pub fn finger_index(&self) -> usize {
+        let finger = match self.finger {
+            Finger::Thumb => 0,
+            Finger::Index => 1,
+            Finger::Middle => 2,
+            Finger::Ring => 3,
+            Finger::Pinky => 4,
+        };
+        if self.hand == 0 {
+            4 - finger
+        } else {
+            5 + finger
+        }
+    }
+
+    /// Straight line distance to another key, in key widths
+    #[must_use]
+    pub fn distance(&self, other: &Key) -> f64 {
+        f64::from(self.column.abs_diff(other.column)).hypot(f64::from(self.row.abs_diff(other.row)))
+    }
+}
+
+/// Short names of each finger, in the order of `Key::finger_index`
+pub const FINGER_NAMES: [&str; 10] = ["LP", "LR", "LM", "LI", "LT", "RT", "RI", "RM", "RR", "RP"];
+
+/// Names of each row, in the order of `Key::row`
+pub const ROW_NAMES: [&str; 4] = ["Top", "Home", "Bottom", "Thumb"];
+
 /// Args that can be used
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -27,7 +68,8 @@ pub struct Args {
     #[arg(short, long, default_value = "whirl.txt")]
     pub layout: String,
 
-    /// Which corpus to use
+    /// Which corpus to use. Several corpora can be blended with weights,
+    /// e.g. `mr.txt:0.6,code:0.3,chat:0.1`. Corpora can be text, or n-gram JSON from other analyzers
     #[arg(short, long, default_value = "mr.txt")]
     pub corpus: String,
 
@@ -35,7 +77,7 @@ pub struct Args {
     #[arg(default_value = "analyze")]
     pub command: String,
 
-    /// For generation. How many swaps the analyser will do
+    /// For generation. How many swaps the analyser will do, or generations for `pareto`
     #[arg(short, long, default_value_t = 500)]
     pub iterations: u64,
 
@@ -47,9 +89,111 @@ pub struct Args {
     #[arg(long, default_value_t = 0.99)]
     pub cooling: f64,
 
-    /// Whether to use compact formatting
+    /// Scoring config file in TOML. The built-in defaults are used without one.
+    /// Scores are raw corpus counts unless `scale` under `[scoring]` is set, for analysis and
+    /// generation alike
+    #[arg(long)]
+    pub config: Option<String>,
+
+    /// Whether to use compact formatting. Analysing and generating show a few line summary
     #[arg(long, action)]
     pub compact: bool,
+
+    /// How results are printed. JSON and CSV include every stat, and the settings used
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// For compare and diff. Layouts to compare, separated by commas. Every layout in the
+    /// layout directory is compared without it. Diff takes the old layout then the new one
+    #[arg(long, value_delimiter = ',')]
+    pub layouts: Vec<String>,
+
+    /// For compare. Layout the others are compared against. The first layout without it
+    #[arg(long)]
+    pub baseline: Option<String>,
+
+    /// For compare. Stat to sort the layouts by, as named in the table. Highest first
+    #[arg(long)]
+    pub sort: Option<String>,
+
+    /// For compare. Sort lowest first instead
+    #[arg(long, action)]
+    pub ascending: bool,
+
+    /// Which ngrams the ngram commands list
+    #[command(flatten)]
+    pub ngram_filter: NgramFilter,
+
+    /// How the raw corpus is normalised before analysis
+    #[command(flatten)]
+    pub normalisation: Normalisation,
+
+    /// Where corpora and layouts given by name are found
+    #[command(flatten)]
+    pub dirs: Directories,
+}
+
+/// Where files are looked for when they're given by name rather than by path
+#[derive(clap::Args, Debug, Clone)]
+pub struct Directories {
+    /// Directory raw corpora are read from
+    #[arg(long, default_value = "corpora/raw")]
+    pub corpus_dir: PathBuf,
+
+    /// Directory filtered corpora are cached in
+    #[arg(long, default_value = "corpora/filtered")]
+    pub cache_dir: PathBuf,
+
+    /// Directory layouts are read from
+    #[arg(long, default_value = "layouts")]
+    pub layout_dir: PathBuf,
+}
+impl Default for Directories {
+    fn default() -> Self {
+        Directories {
+            corpus_dir: PathBuf::from("corpora/raw"),
+            cache_dir: PathBuf::from("corpora/filtered"),
+            layout_dir: PathBuf::from("layouts"),
+        }
+    }
+}
+impl Directories {
+    /// Find a layout. Looks in the layout directory first, then treats the name as a path
+    pub fn layout_path(&self, layout: &str) -> PathBuf {
+        find_file(&self.layout_dir, layout)
+    }
+
+    /// Names of every layout in the layout directory, sorted
+    pub fn layouts(&self) -> Vec<String> {
+        let mut layouts: Vec<String> = fs::read_dir(&self.layout_dir)
+            .expect("couldn't read layout directory")
+            .map(|entry| entry.expect("couldn't read layout directory").path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+            .map(|path| {
+                path.file_name()
+                    .expect("layout files have names")
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        layouts.sort();
+        layouts
+    }
+
+    /// Find a corpus. Looks in the corpus directory first, then treats the name as a path
+    pub fn corpus_path(&self, corpus: &str) -> PathBuf {
+        find_file(&self.corpus_dir, corpus)
+    }
+}
+
+/// Use the file in the directory if it exists, otherwise use the name as a path
+fn find_file(dir: &Path, name: &str) -> PathBuf {
+    let in_dir = dir.join(name);
+    if in_dir.exists() {
+        in_dir
+    } else {
+        PathBuf::from(name)
+    }
 }
 
 #[derive(Eq, Hash, PartialEq, PartialOrd, Debug, Clone)]
@@ -61,6 +205,20 @@ pub enum Finger {
     Pinky,
 }
 
+impl Finger {
+    /// How far the finger reaches up, shortest first: pinky, index, ring then middle
+    #[must_use]
+    pub fn length(&self) -> u8 {
+        match self {
+            Finger::Thumb => 0,
+            Finger::Pinky => 1,
+            Finger::Index => 2,
+            Finger::Ring => 3,
+            Finger::Middle => 4,
+        }
+    }
+}
+
 /// General use struct for all layouts
 #[derive(Default, Clone, Debug)]
 pub struct Layout {
@@ -133,6 +291,23 @@ pub struct Stats {
     /// Half Scissor Skipgram
     /// e.g. `k_u` on Qwerty
     hss: i64,
+    /// Full scissor bigram with the shorter finger on the top row
+    /// e.g. `qc` on Qwerty
+    fsb_short_up: i64,
+    /// Full scissor bigram with the longer finger on the top row
+    /// e.g. `ex` on Qwerty
+    fsb_long_up: i64,
+    /// Half scissor bigram with the shorter finger on the higher row
+    /// e.g. `ac` on Qwerty
+    hsb_short_up: i64,
+    /// Half scissor bigram with the longer finger on the higher row
+    /// e.g. `il` on Qwerty
+    hsb_long_up: i64,
+    /// Bigram on one hand jumping between the top and bottom rows
+    /// e.g. `qv` on Qwerty
+    row_jump: i64,
+    /// Row jumps weighted by direction and finger pair
+    row_jump_pen: i64,
     /// Two keys on one hand rolling inwards, one key on the other hand
     /// e.g. `ask` on Qwerty
     inroll: i64,
@@ -142,6 +317,18 @@ pub struct Stats {
     /// Alternation
     /// e.g. `the` on Qwerty
     alt: i64,
+    /// Alternation where the outer keys are fine together
+    /// e.g. `sid` on Qwerty
+    alt_clean: i64,
+    /// Alternation where the outer keys are a same finger skipgram
+    /// e.g. `eid` on Qwerty
+    alt_sfs: i64,
+    /// Alternation where the outer keys are a lateral stretch
+    /// e.g. `dog` on Qwerty
+    alt_lateral: i64,
+    /// Alternation where the outer keys are a scissor
+    /// e.g. `ric` on Qwerty
+    alt_scissor: i64,
     /// Three keys on one hand rolling inwards
     /// e.g. `wer` on Qwerty
     inthreeroll: i64,
@@ -154,29 +341,200 @@ pub struct Stats {
     /// Keys redirecting, but not without a thumb or index
     /// e.g. `was` on Qwerty
     weak_red: i64,
+    /// Redirect with the pinky in the middle
+    /// e.g. `sas` on Qwerty
+    pinky_off: i64,
+    /// Three keys on one hand with a same finger bigram
+    /// e.g. `ced` on Qwerty
+    sfb_red: i64,
+    /// Redirect without the index finger, but with a thumb
+    /// e.g. `s d` on Qwerty
+    bad_red: i64,
+    /// Three keys on one hand, whether a roll or a redirect. Overlaps with those stats,
+    /// so isn't weighted by default
+    /// e.g. `see` on Qwerty
+    onehand: i64,
+    /// Redirect rolling inwards then outwards
+    /// e.g. `sds` on Qwerty
+    in_out_red: i64,
+    /// Hands alternating for four keys in a row
+    /// e.g. `sign` on Qwerty
+    alt_chain: i64,
+    /// A roll on one hand straight into a roll on the other
+    /// e.g. `stil` on Qwerty
+    roll_roll: i64,
+    /// Switching hands into a redirect
+    /// e.g. `ifad` on Qwerty
+    alt_red: i64,
+    /// An SFB followed by an SFS on the same finger
+    /// e.g. `deac` on Qwerty
+    chained_sfb: i64,
+    /// Total count of quadgrams. 0 unless quadgrams are turned on in the config
+    pub quadgrams: u32,
     /// Weighted penalty of usage of each key
     heatmap: i64,
     /// Penalty for utilising each column. To ensure pinky is not too heavy
     column_pen: i64,
+    /// How often each finger is used, indexed by `Key::finger_index`
+    finger_usage: [i64; 10],
+    /// How often each hand is used, left then right
+    hand_usage: [i64; 2],
+    /// How often each row is used: top, home, bottom, thumb
+    row_usage: [i64; 4],
+    /// SFBs on each finger
+    finger_sfb: [i64; 10],
+    /// SFSs on each finger
+    finger_sfs: [i64; 10],
+    /// Each finger's share of fspeed
+    finger_fspeed: [i64; 10],
     /// How many ngrams include thumb
     thumb_stat: u32,
+    /// Key presses on either thumb
+    thumb_usage: i64,
+    /// Bigram between the two thumb keys, as if one thumb pressed both
+    /// e.g. `_*` on Whirl
+    thumb_sfb: i64,
+    /// Roll between a thumb and the other keys on its hand
+    /// e.g. `_to` on Whirl
+    thumb_roll: i64,
     /// Total count of skipgrams
     /// (this may be equal to chars)
     pub skipgrams: u32,
+    /// Skipgram stats at each distance, starting with one letter in between.
+    /// `sfs`, `lss`, `fss` and `hss` are these added up by the weight of each distance
+    pub skip_stats: [SkipStats; MAX_SKIP],
     /// Total count of characters
     pub chars: u32,
     /// Table of how often each ngram occurs
-    pub ngram_table: AHashMap<[char; 3], u32>,
+    /// Shorter ngrams are padded with spaces
+    pub ngram_table: AHashMap<[char; 4], u32>,
     /// Table of how often each bad bigram occurs
     /// SFB, FSB, HSB, LSB
     pub bad_bigrams: AHashMap<[char; 2], u32>,
 }
 
-const INCLUDE_THUMB_ALT: bool = true;
-const INCLUDE_THUMB_ROLL: bool = true;
+/// Names and values of every scalar stat, e.g. `("sfb", stats.sfb)`
+macro_rules! counters {
+    ($stats:ident, $($field:ident),* $(,)?) => {
+        vec![$((stringify!($field).to_owned(), i64::from($stats.$field))),*]
+    };
+}
+
+impl Stats {
+    /// Value of one counter by its name in `counters`
+    #[must_use]
+    pub fn counter(&self, name: &str) -> Option<i64> {
+        self.counters()
+            .into_iter()
+            .find(|(counter, _)| counter == name)
+            .map(|(_, value)| value)
+    }
 
-/// Get hashmap of magic rules from layout name
-pub fn load_magic_rules(layout: &str) -> AHashMap<char, char> {
+    /// Every counter that can be weighted in the score, by name. Per finger, hand and row
+    /// stats are named like `finger_sfb.lp`, `hand_usage.left` and `row_usage.top`, and
+    /// skipgram stats at each distance like `sfs@2`
+    #[must_use]
+    pub fn counters(&self) -> Vec<(String, i64)> {
+        let mut counters = counters!(
+            self,
+            fspeed,
+            sfb,
+            sfr,
+            sfs,
+            lsb,
+            lss,
+            fsb,
+            fss,
+            hsb,
+            hss,
+            fsb_short_up,
+            fsb_long_up,
+            hsb_short_up,
+            hsb_long_up,
+            row_jump,
+            row_jump_pen,
+            inroll,
+            outroll,
+            alt,
+            alt_clean,
+            alt_sfs,
+            alt_lateral,
+            alt_scissor,
+            inthreeroll,
+            outthreeroll,
+            red,
+            weak_red,
+            pinky_off,
+            sfb_red,
+            bad_red,
+            onehand,
+            in_out_red,
+            alt_chain,
+            roll_roll,
+            alt_red,
+            chained_sfb,
+            heatmap,
+            column_pen,
+            thumb_stat,
+            thumb_usage,
+            thumb_sfb,
+            thumb_roll,
+        );
+        let fingers = FINGER_NAMES.map(str::to_lowercase);
+        for (name, values) in [
+            ("finger_usage", &self.finger_usage),
+            ("finger_sfb", &self.finger_sfb),
+            ("finger_sfs", &self.finger_sfs),
+            ("finger_fspeed", &self.finger_fspeed),
+        ] {
+            counters.extend(
+                fingers
+                    .iter()
+                    .zip(values)
+                    .map(|(finger, value)| (format!("{name}.{finger}"), *value)),
+            );
+        }
+        counters.extend(
+            ["left", "right"]
+                .iter()
+                .zip(self.hand_usage)
+                .map(|(hand, value)| (format!("hand_usage.{hand}"), value)),
+        );
+        counters.extend(
+            ROW_NAMES
+                .iter()
+                .zip(self.row_usage)
+                .map(|(row, value)| (format!("row_usage.{}", row.to_lowercase()), value)),
+        );
+        for (distance, skip_stats) in self.skip_stats.iter().enumerate() {
+            let distance = distance + 1;
+            counters.extend([
+                (format!("sfs@{distance}"), skip_stats.sfs),
+                (format!("lss@{distance}"), skip_stats.lss),
+                (format!("fss@{distance}"), skip_stats.fss),
+                (format!("hss@{distance}"), skip_stats.hss),
+            ]);
+        }
+        counters
+    }
+}
+
+/// Skipgram stats at one distance
+#[derive(Default, Clone, Debug)]
+pub struct SkipStats {
+    /// Total count of skipgrams at this distance
+    pub skipgrams: u32,
+    pub sfs: i64,
+    pub lss: i64,
+    pub fss: i64,
+    pub hss: i64,
+}
+
+/// Most letters a skipgram can have in between
+pub const MAX_SKIP: usize = 8;
+
+/// Get hashmap of magic rules from layout file
+pub fn load_magic_rules(layout: impl AsRef<Path>) -> AHashMap<char, char> {
     let layout_letters = load_layout_letters(layout);
     let magic_rules_raw = layout_letters[36..].split('\n').filter(|s| !s.is_empty());
     let mut magic_rules: AHashMap<char, char> = AHashMap::default();
@@ -190,8 +548,29 @@ pub fn load_magic_rules(layout: &str) -> AHashMap<char, char> {
     magic_rules
 }
 
-/// Get array of layout letters from layout name
-pub fn load_layout(layout: &str) -> [char; 32] {
+/// Write a layout file that `load_layout` and `load_magic_rules` can read back
+pub fn save_layout(
+    path: impl AsRef<Path>,
+    layout: &[char; 32],
+    magic_rules: &AHashMap<char, char>,
+) -> io::Result<()> {
+    let mut file = String::new();
+    for row in layout[..30].chunks(10) {
+        let keys: Vec<String> = row.iter().map(char::to_string).collect();
+        file.push_str(&keys.join(" "));
+        file.push('\n');
+    }
+    file.push_str(&format!("      {}     {}\n", layout[30], layout[31]));
+    let mut rules: Vec<(&char, &char)> = magic_rules.iter().collect();
+    rules.sort();
+    for (key, rule) in rules {
+        file.push_str(&format!("{key}{rule}\n"));
+    }
+    fs::write(path, file)
+}
+
+/// Get array of layout letters from layout file
+pub fn load_layout(layout: impl AsRef<Path>) -> [char; 32] {
     let layout_letters = load_layout_letters(layout);
     // has to be 37 because ⎵ is a few extra bytes
     layout_letters[..35]
@@ -202,9 +581,9 @@ pub fn load_layout(layout: &str) -> [char; 32] {
         .expect("couldn't read layout")
 }
 
-/// get a string of the layout from the layout name
-fn load_layout_letters(layout: &str) -> String {
-    let layout_letters: String = fs::read_to_string("layouts/".to_owned() + layout)
+/// get a string of the layout from the layout file
+fn load_layout_letters(layout: impl AsRef<Path>) -> String {
+    let layout_letters: String = fs::read_to_string(layout)
         .expect("couldn't read layout")
         .replace([' ', ' '], "")
         .chars()
@@ -212,8 +591,18 @@ fn load_layout_letters(layout: &str) -> String {
     layout_letters
 }
 
+pub fn load_four_keys(letters: &str) -> (Key, Key, Key, Key) {
+    let layout_raw = &load_layout("layouts/whirl.txt");
+    let table = layout_raw_to_table(layout_raw);
+    let key1 = table[&letters.chars().next().unwrap()].clone();
+    let key2 = table[&letters.chars().nth(1).unwrap()].clone();
+    let key3 = table[&letters.chars().nth(2).unwrap()].clone();
+    let key4 = table[&letters.chars().nth(3).unwrap()].clone();
+    (key1, key2, key3, key4)
+}
+
 pub fn load_three_keys(letters: &str) -> (Key, Key, Key) {
-    let layout_raw = &load_layout("whirl.txt");
+    let layout_raw = &load_layout("layouts/whirl.txt");
     let table = layout_raw_to_table(layout_raw);
     let key1 = table[&letters.chars().next().unwrap()].clone();
     let key2 = table[&letters.chars().nth(1).unwrap()].clone();
@@ -222,60 +611,28 @@ pub fn load_three_keys(letters: &str) -> (Key, Key, Key) {
 }
 
 pub fn load_two_keys(letters: &str) -> (Key, Key) {
-    let layout_raw = &load_layout("whirl.txt");
+    let layout_raw = &load_layout("layouts/whirl.txt");
     let table = layout_raw_to_table(layout_raw);
     let key1 = table[&letters.chars().next().unwrap()].clone();
     let key2 = table[&letters.chars().nth(1).unwrap()].clone();
     (key1, key2)
 }
 
-/// Filter corpus with only letters from the layout and processes magic rules
-fn filter_corpus(corpus_name: &str, layout_raw: &[char; 32]) -> String {
-    println!("{}", "corpora/raw/".to_owned() + corpus_name);
-    let corpus: String = fs::read_to_string("corpora/raw/".to_owned() + corpus_name)
-        .expect("error reading corpus")
-        .replace("\n\n", "")
-        .replace(' ', "_")
-        .chars()
-        .flat_map(|ch| {
-            if ch.is_ascii_uppercase() {
-                // Replace uppercase letters with "*" followed by lowercase
-                format!("*{}", ch.to_ascii_lowercase())
-                    .chars()
-                    .collect::<Vec<_>>()
-            } else {
-                vec![ch]
-            }
-        })
-        .filter(|ch| layout_raw.contains(ch))
-        .collect();
-    let mut write_file =
-        File::create("corpora/filtered/".to_owned() + corpus_name).expect("couldn't write corpus");
-    let _ = write_file.write_all(corpus.as_bytes());
-    corpus.to_string()
-}
-
-/// Load corpus from corpus name, and filter it with the layout name if it has not been previously filtered
-pub fn load_corpus(corpus_name: &str, layout_name: &str) -> String {
-    let layout = load_layout(layout_name);
-    match fs::read_to_string("corpora/filtered/".to_owned() + corpus_name) {
-        Ok(corpus) => corpus,
-        Err(_) => {
-            println!("couldn't find corpus, now loading");
-            filter_corpus(corpus_name, &layout)
-        }
-    }
-}
-
 /// Standalone function that converts functions from Whirl to something else so I can try it out
 // TODO make it work for other layouts
-pub fn convert_corpus(new_layout_name: &str, corpus_name: &str) {
-    let old_layout_name: &String = &String::from("whirl.txt");
-    let old_layout = load_layout(old_layout_name);
-    let new_layout = load_layout(new_layout_name);
-    let old_magic_rules = load_magic_rules(old_layout_name);
-    let new_magic_rules = load_magic_rules(new_layout_name);
-    let mut corpus = load_corpus(corpus_name, old_layout_name);
+pub fn convert_corpus(
+    new_layout_name: &str,
+    corpus_name: &str,
+    settings: &Normalisation,
+    dirs: &Directories,
+) {
+    let old_layout_path = dirs.layout_path("whirl.txt");
+    let new_layout_path = dirs.layout_path(new_layout_name);
+    let old_layout = load_layout(&old_layout_path);
+    let new_layout = load_layout(&new_layout_path);
+    let old_magic_rules = load_magic_rules(&old_layout_path);
+    let new_magic_rules = load_magic_rules(&new_layout_path);
+    let mut corpus = load_corpus(corpus_name, &old_layout, settings, dirs, false);
 
     for letter in new_layout {
         let rule: [char; 2] = match new_magic_rules.get(&letter) {
@@ -311,17 +668,22 @@ pub fn convert_corpus(new_layout_name: &str, corpus_name: &str) {
 mod tests {
     use ahash::AHashMap;
 
-    use crate::{load_layout, load_layout_letters, load_magic_rules};
+    use std::path::PathBuf;
+
+    use crate::{
+        load_layout, load_layout_letters, load_magic_rules, load_two_keys, save_layout,
+        stats::layout_raw_to_table, Directories,
+    };
 
     #[test]
     fn test_load_letters() {
-        let layout_string = load_layout_letters("whirl.txt");
+        let layout_string = load_layout_letters("layouts/whirl.txt");
         let expected_layout_string = "qgdfvzluoy\nnsthm'reai\nbcpwkxj;.,\n_*\nwh\ny,\nue\ngs\n'r\n";
         assert_eq!(layout_string, expected_layout_string);
     }
     #[test]
     fn test_load_layout() {
-        let layout_array = load_layout("whirl.txt");
+        let layout_array = load_layout("layouts/whirl.txt");
         let expected_layout_array = [
             'q', 'g', 'd', 'f', 'v', 'z', 'l', 'u', 'o', 'y', 'n', 's', 't', 'h', 'm', '\'', 'r',
             'e', 'a', 'i', 'b', 'c', 'p', 'w', 'k', 'x', 'j', ';', '.', ',', '_', '*',
@@ -330,9 +692,67 @@ mod tests {
     }
     #[test]
     fn test_load_magic_rules() {
-        let rules = load_magic_rules("whirl.txt");
+        let rules = load_magic_rules("layouts/whirl.txt");
         let expected_rules =
             AHashMap::from([('w', 'h'), ('u', 'e'), ('g', 's'), ('y', ','), ('\'', 'r')]);
         assert_eq!(rules, expected_rules);
     }
+    #[test]
+    fn test_layout_path() {
+        let dirs = Directories::default();
+        assert_eq!(
+            dirs.layout_path("whirl.txt"),
+            PathBuf::from("layouts/whirl.txt")
+        );
+        assert_eq!(
+            dirs.layout_path("layouts/qwerty.txt"),
+            PathBuf::from("layouts/qwerty.txt")
+        );
+    }
+    #[test]
+    fn test_layouts() {
+        let layouts = Directories::default().layouts();
+        assert!(layouts.contains(&"whirl.txt".to_owned()));
+        assert!(layouts.is_sorted());
+        // Only text files are layouts
+        let dirs = Directories {
+            layout_dir: std::env::temp_dir().join("chogalyzer_test_layouts"),
+            ..Directories::default()
+        };
+        std::fs::create_dir_all(&dirs.layout_dir).unwrap();
+        std::fs::write(dirs.layout_dir.join("notes.md"), "").unwrap();
+        assert!(dirs.layouts().is_empty());
+        std::fs::remove_dir_all(&dirs.layout_dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_layout() {
+        let layout = load_layout("layouts/whirl.txt");
+        let magic_rules = load_magic_rules("layouts/whirl.txt");
+        let path = std::env::temp_dir().join("chogalyzer_test_save_layout.txt");
+        save_layout(&path, &layout, &magic_rules).unwrap();
+        assert_eq!(load_layout(&path), layout);
+        assert_eq!(load_magic_rules(&path), magic_rules);
+        std::fs::remove_file(path).unwrap();
+        let missing = std::env::temp_dir().join("chogalyzer_missing_dir/layout.txt");
+        assert!(save_layout(missing, &layout, &magic_rules).is_err());
+    }
+
+    #[test]
+    fn test_finger_index() {
+        let layout = load_layout("layouts/whirl.txt");
+        let table = layout_raw_to_table(&layout);
+        let fingers: Vec<usize> = layout.iter().map(|c| table[c].finger_index()).collect();
+        assert_eq!(fingers[0..10], [0, 1, 2, 3, 3, 6, 6, 7, 8, 9]);
+        assert_eq!(fingers[30..32], [4, 5]);
+    }
+    #[test]
+    fn test_key_distance() {
+        let (u, e) = load_two_keys("ue");
+        assert_eq!(u.distance(&e), 1.0);
+        let (y, comma) = load_two_keys("y,");
+        assert_eq!(y.distance(&comma), 2.0);
+        let (h, v) = load_two_keys("hv");
+        assert_eq!(h.distance(&v), 2.0_f64.sqrt());
+    }
 }
@@ -1,76 +1,260 @@
 use chogalyzer::{
-    convert_corpus, generation, load_corpus, load_layout, load_magic_rules,
-    output::{self, LayoutDisplay},
-    stats, Args,
+    config::{load_config, Config},
+    convert_corpus,
+    corpus::{corpora_dropped_chars, load_corpora, parse_corpora},
+    diff, generation, load_layout, load_magic_rules,
+    output::{self, Settings},
+    repl, stats, Args, Layout,
 };
 use clap::Parser;
+use std::path::Path;
 
 fn main() {
     let args = Args::parse();
-    let corpus = load_corpus(&args.corpus, &args.layout);
-    let layout_raw = load_layout(&args.layout);
-    let magic_rules = load_magic_rules(&args.layout);
-    let stats = stats::analyze(corpus.clone(), layout_raw, &args.command, &magic_rules);
-    let mut ngram_vec: Vec<([char; 3], u32)> = stats.ngram_table.clone().into_iter().collect();
+    let layout_path = args.dirs.layout_path(&args.layout);
+    let layout_raw = load_layout(&layout_path);
+    let magic_rules = load_magic_rules(&layout_path);
+    let config = load_config(args.config.as_ref());
+    if let Err(error) = parse_corpora(&args.corpus) {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
+    // These don't analyse the layout, or load their own corpora
+    match args.command.as_str() {
+        // Filters the corpus again, ignoring the cache
+        "corpus-rebuild" => {
+            load_corpora(
+                &args.corpus,
+                &layout_raw,
+                &args.normalisation,
+                &args.dirs,
+                true,
+                config.window_width(),
+            );
+            println!("rebuilt {}", args.corpus);
+            return;
+        }
+        // Analyses several layouts and displays them side by side
+        "compare" => return compare_layouts(&args, &config),
+        // Explains what changed between two layouts
+        "diff" => return diff_layouts(&args, &config),
+        _ => {}
+    }
+    let corpus = load_corpora(
+        &args.corpus,
+        &layout_raw,
+        &args.normalisation,
+        &args.dirs,
+        false,
+        config.window_width(),
+    );
+    if corpus.windows.is_empty() {
+        eprintln!("corpus is empty");
+        std::process::exit(1);
+    }
+    let command = output::ngram_command_name(&args.command);
+    let stats = stats::analyze(&corpus, layout_raw, command, &magic_rules, &config);
+    let mut ngram_vec: Vec<([char; 4], u32)> = stats.ngram_table.clone().into_iter().collect();
     ngram_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
 
-    match args.command.as_str() {
+    match command {
         // Basic command, analyses a layout and displays
-        "analyze" => LayoutDisplay::new(
-            args.layout.clone().strip_suffix(".txt").unwrap(),
-            layout_raw,
-            &stats,
-            &magic_rules,
-        )
-        .full(),
+        "analyze" => output::print_layout(
+            &layout_name(&args.layout),
+            &Layout {
+                layout: layout_raw,
+                magic: magic_rules,
+                stats,
+            },
+            &Settings::new(&args, &config),
+            &args,
+        ),
         // Generates a layout using sim annealing and displays. Algorithm should be an arg... TODO
         "generate" => {
-            let layout = generation::generate_threads(
+            let mut layout = generation::generate_threads(
                 layout_raw,
                 &corpus,
+                &config,
                 args.iterations,
                 args.magic_rules,
                 args.cooling,
                 chogalyzer::Algorithm::SimAnnealing,
             );
-            LayoutDisplay::new(
-                // name
-                layout.layout[10..15].iter().collect::<String>().as_str(),
+            layout.stats = stats::analyze(
+                &corpus,
                 layout.layout,
-                &stats::analyze(corpus.clone(), layout.layout, &args.command, &layout.magic),
+                &args.command,
                 &layout.magic,
-            )
-            .full();
+                &config,
+            );
+            output::print_layout(
+                // name
+                layout.layout[10..15].iter().collect::<String>().as_str(),
+                &layout,
+                &Settings::generation(&args, &config),
+                &args,
+            );
         }
-        // Standalone function that converts functions from Whirl to something else so I can try it out
-        "convert" => convert_corpus(&args.layout, &args.corpus),
-
-        // all of these get a list of the most common examples of each ngram
-        "sfb" => output::print_ngrams(&ngram_vec, stats.chars, "SFB".to_string(), &args),
-        "sfr" => output::print_ngrams(&ngram_vec, stats.chars, "SFR".to_string(), &args),
-        "sfs" => output::print_ngrams(&ngram_vec, stats.skipgrams, "SFS".to_string(), &args),
-        "lsbs" => output::print_ngrams(&ngram_vec, stats.chars, "LSB".to_string(), &args),
-        "lss" => output::print_ngrams(&ngram_vec, stats.skipgrams, "LSS".to_string(), &args),
-        "fsb" => output::print_ngrams(&ngram_vec, stats.chars, "FSB".to_string(), &args),
-        "hsb" => output::print_ngrams(&ngram_vec, stats.chars, "HSB".to_string(), &args),
-        "fss" => output::print_ngrams(&ngram_vec, stats.skipgrams, "FSS".to_string(), &args),
-        "alt" => output::print_ngrams(&ngram_vec, stats.chars, "Alt".to_string(), &args),
-        "inroll" => output::print_ngrams(&ngram_vec, stats.chars, "Inroll".to_string(), &args),
-        "outroll" => output::print_ngrams(&ngram_vec, stats.chars, "Outroll".to_string(), &args),
-        "inthreeroll" => {
-            output::print_ngrams(&ngram_vec, stats.chars, "Inthreeroll".to_string(), &args);
+        // Generates layouts optimising several stats at once, and displays the Pareto front
+        "pareto" => {
+            let front = generation::pareto::generate_pareto(
+                layout_raw,
+                &corpus,
+                &config,
+                args.iterations,
+                args.magic_rules,
+            );
+            output::print_pareto_front(
+                &front,
+                &config,
+                &Settings::generation(&args, &config),
+                args.format,
+            );
         }
-        "outthreeroll" => {
-            output::print_ngrams(&ngram_vec, stats.chars, "Outthreeroll".to_string(), &args);
+        // Edit the layout interactively, keeping the corpus loaded
+        "repl" => {
+            let mut session = repl::Session::new(layout_raw, magic_rules);
+            repl::run(
+                &layout_name(&args.layout),
+                &mut session,
+                &corpus,
+                &config,
+                &args,
+            );
         }
-        "red" => output::print_ngrams(&ngram_vec, stats.chars, "Red".to_string(), &args),
-        "weak" => output::print_ngrams(&ngram_vec, stats.chars, "Weak".to_string(), &args),
-        "thumb" => output::print_ngrams(&ngram_vec, stats.chars, "Thumb".to_string(), &args),
-        "bigrams" => output::print_ngrams(&ngram_vec, stats.chars, "Bigrams".to_string(), &args),
-        "skipgrams" => {
-            output::print_ngrams(&ngram_vec, stats.skipgrams, "Skipgrams".to_string(), &args)
+        // Information about the corpus itself rather than the layout
+        "corpus-stats" => {
+            let (dropped, unfiltered) =
+                corpora_dropped_chars(&args.corpus, &layout_raw, &args.normalisation, &args.dirs);
+            output::print_corpus_stats(&corpus, &dropped, unfiltered);
         }
-        "trigrams" => output::print_ngrams(&ngram_vec, stats.chars, "Trigrams".to_string(), &args),
-        _ => println!("invalid command"),
+        // Standalone function that converts functions from Whirl to something else so I can try it out
+        "convert" => convert_corpus(&args.layout, &args.corpus, &args.normalisation, &args.dirs),
+
+        // all of these get a list of the most common examples of each ngram
+        command => match output::ngram_command(command, &stats) {
+            Some((title, total)) => {
+                let settings = Settings::new(&args, &config);
+                output::print_ngrams(
+                    &ngram_vec,
+                    total,
+                    title.to_string(),
+                    &layout_raw,
+                    &settings,
+                    &args,
+                );
+            }
+            None => println!("invalid command"),
+        },
     }
 }
+
+/// Analyses several layouts, each with the corpus filtered to its own keys, and displays them
+/// side by side
+fn compare_layouts(args: &Args, config: &Config) {
+    let names = if args.layouts.is_empty() {
+        args.dirs.layouts()
+    } else {
+        args.layouts.clone()
+    };
+    if names.is_empty() {
+        eprintln!("no layouts in {}", args.dirs.layout_dir.display());
+        std::process::exit(1);
+    }
+    let layouts: Vec<(String, Layout)> = names
+        .iter()
+        .map(|name| {
+            let path = args.dirs.layout_path(name);
+            let layout_raw = load_layout(&path);
+            // Each layout filters the corpus to its own keys
+            let corpus = load_corpora(
+                &args.corpus,
+                &layout_raw,
+                &args.normalisation,
+                &args.dirs,
+                false,
+                config.window_width(),
+            );
+            let magic_rules = load_magic_rules(&path);
+            let stats = stats::analyze(&corpus, layout_raw, "compare", &magic_rules, config);
+            let layout = Layout {
+                layout: layout_raw,
+                magic: magic_rules,
+                stats,
+            };
+            (layout_name(name), layout)
+        })
+        .collect();
+    let baseline = args
+        .baseline
+        .as_deref()
+        .map_or_else(|| layouts[0].0.clone(), layout_name);
+    if let Err(error) = output::print_comparison(
+        &layouts,
+        &baseline,
+        args.sort.as_deref(),
+        args.ascending,
+        &Settings::new(args, config),
+        args.format,
+    ) {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
+}
+
+/// Explains what changed between two layouts
+fn diff_layouts(args: &Args, config: &Config) {
+    let [old, new] = args.layouts.as_slice() else {
+        eprintln!("diff needs two layouts, e.g. --layouts whirl.txt,new.txt");
+        std::process::exit(1);
+    };
+    let (old_path, new_path) = (args.dirs.layout_path(old), args.dirs.layout_path(new));
+    let (old_raw, new_raw) = (load_layout(&old_path), load_layout(&new_path));
+    let (old_magic, new_magic) = (load_magic_rules(&old_path), load_magic_rules(&new_path));
+    let moved = diff::moved_keys(&old_raw, &new_raw).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        std::process::exit(1);
+    });
+    // Both layouts have the same keys, so the corpus filtered to the old one fits the new one
+    let corpus = load_corpora(
+        &args.corpus,
+        &old_raw,
+        &args.normalisation,
+        &args.dirs,
+        false,
+        config.window_width(),
+    );
+    let changes = diff::changed_ngrams(
+        &corpus,
+        (&old_raw, &old_magic),
+        (&new_raw, &new_magic),
+        config,
+    );
+    let old_layout = Layout {
+        layout: old_raw,
+        stats: stats::analyze(&corpus, old_raw, "diff", &old_magic, config),
+        magic: old_magic,
+    };
+    let new_layout = Layout {
+        layout: new_raw,
+        stats: stats::analyze(&corpus, new_raw, "diff", &new_magic, config),
+        magic: new_magic,
+    };
+    output::print_diff(
+        (&layout_name(old), &old_layout),
+        (&layout_name(new), &new_layout),
+        &moved,
+        &changes,
+        &Settings::new(args, config),
+        args.format,
+    );
+}
+
+/// Name of a layout to display, from its file name or path
+fn layout_name(layout: &str) -> String {
+    Path::new(layout)
+        .file_stem()
+        .expect("layouts have file names")
+        .to_string_lossy()
+        .into_owned()
+}
@@ -1,38 +1,981 @@
-use crate::Args;
+use crate::config::Config;
+use crate::corpus::{Corpus, Normalisation};
+use crate::diff::NgramChange;
+use crate::stats::{layout_raw_to_table, SKIPPED};
 use crate::Stats;
+use crate::{Args, Key, Layout};
+use crate::{FINGER_NAMES, ROW_NAMES};
 use ahash::AHashMap;
-use tabled::{builder::Builder, col, settings::Style};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tabled::{builder::Builder, col, row, settings::Style};
 
-/// When the user wants a list of the most frequent of a type of ngram, displays them in a table
-pub fn print_ngrams(vec: &[([char; 3], u32)], ngrams: u32, title: String, args: &Args) {
+/// How results are printed
+#[derive(ValueEnum, Default, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum OutputFormat {
+    /// Tables for reading
+    #[default]
+    Table,
+    /// JSON for other programs
+    Json,
+    /// CSV for spreadsheets. Lists of ngrams and layouts get a row each, and a single layout
+    /// gets a row per field, named like `settings.config.quadgrams`
+    Csv,
+}
+
+/// What a result was worked out with, for JSON and CSV
+#[derive(Serialize)]
+pub struct Settings<'a> {
+    corpus: &'a str,
+    normalisation: &'a Normalisation,
+    config: &'a Config,
+    /// Only for commands that generate layouts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation: Option<Generation>,
+}
+
+/// Settings of generating layouts
+#[derive(Serialize)]
+struct Generation {
+    iterations: u64,
+    magic_rules: usize,
+    cooling: f64,
+}
+
+impl Settings<'_> {
+    /// Settings for analysing layouts
+    #[must_use]
+    pub fn new<'a>(args: &'a Args, config: &'a Config) -> Settings<'a> {
+        Settings {
+            corpus: &args.corpus,
+            normalisation: &args.normalisation,
+            config,
+            generation: None,
+        }
+    }
+
+    /// Settings for generating layouts
+    #[must_use]
+    pub fn generation<'a>(args: &'a Args, config: &'a Config) -> Settings<'a> {
+        Settings {
+            generation: Some(Generation {
+                iterations: args.iterations,
+                magic_rules: args.magic_rules,
+                cooling: args.cooling,
+            }),
+            ..Settings::new(args, config)
+        }
+    }
+}
+
+/// A layout and everything analysed about it, for JSON and CSV
+#[derive(Serialize)]
+struct Report {
+    name: String,
+    /// Keys row by row from the top left, then the thumbs
+    layout: String,
+    magic: BTreeMap<char, char>,
+    score: f64,
+    chars: u32,
+    skipgrams: u32,
+    quadgrams: u32,
+    /// Every counter, named like its weight in the config
+    counters: BTreeMap<String, i64>,
+    /// Stats as shown in tables, mostly percentages
+    summary: BTreeMap<String, f64>,
+}
+
+impl Report {
+    fn new(name: &str, layout: &Layout) -> Report {
+        let stats = &layout.stats;
+        Report {
+            name: name.to_owned(),
+            layout: layout.layout.iter().collect(),
+            magic: layout.magic.iter().map(|(&k, &v)| (k, v)).collect(),
+            score: stats.score,
+            chars: stats.chars,
+            skipgrams: stats.skipgrams,
+            quadgrams: stats.quadgrams,
+            counters: stats.counters().into_iter().collect(),
+            summary: get_stats_hash(stats).into_iter().collect(),
+        }
+    }
+}
+
+/// A result with the settings it was worked out with
+#[derive(Serialize)]
+struct Output<'a, T> {
+    settings: &'a Settings<'a>,
+    #[serde(flatten)]
+    result: T,
+}
+
+/// Prints a result as JSON
+fn print_json(result: &impl Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(result).expect("results can be serialised")
+    );
+}
+
+/// Prints records as CSV
+fn print_csv(records: &[Vec<String>]) {
+    for record in records {
+        let fields: Vec<String> = record.iter().map(|field| csv_field(field)).collect();
+        println!("{}", fields.join(","));
+    }
+}
+
+/// A CSV field, quoted if it has a comma, quote or newline. Layouts often have commas
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Every value in a result, named by its path like `settings.config.quadgrams`
+fn flatten(result: &impl Serialize) -> Vec<(String, String)> {
+    fn walk(path: String, value: Value, fields: &mut Vec<(String, String)>) {
+        let join = |key: &str| {
+            if path.is_empty() {
+                key.to_owned()
+            } else {
+                format!("{path}.{key}")
+            }
+        };
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    walk(join(&key), value, fields);
+                }
+            }
+            Value::Array(values) => {
+                for (i, value) in values.into_iter().enumerate() {
+                    walk(join(&i.to_string()), value, fields);
+                }
+            }
+            Value::String(value) => fields.push((path, value)),
+            Value::Null => fields.push((path, String::new())),
+            value => fields.push((path, value.to_string())),
+        }
+    }
+    let mut fields = Vec::new();
+    let value = serde_json::to_value(result).expect("results can be serialised");
+    walk(String::new(), value, &mut fields);
+    fields
+}
+
+/// Prints everything about a layout, or a summary of it if compact
+pub fn print_layout(name: &str, layout: &Layout, settings: &Settings, args: &Args) {
+    let output = Output {
+        settings,
+        result: Report::new(name, layout),
+    };
+    match args.format {
+        OutputFormat::Table => {
+            let display = LayoutDisplay::new(name, layout.layout, &layout.stats, &layout.magic);
+            if args.compact {
+                display.simple_display();
+            } else {
+                display.full();
+            }
+        }
+        OutputFormat::Json => print_json(&output),
+        OutputFormat::Csv => {
+            let mut records = vec![vec!["field".to_owned(), "value".to_owned()]];
+            records.extend(
+                flatten(&output)
+                    .into_iter()
+                    .map(|(field, value)| vec![field, value]),
+            );
+            print_csv(&records);
+        }
+    }
+}
+
+/// One layout on a Pareto front, for JSON
+#[derive(Serialize)]
+struct ParetoLayout {
+    layout: String,
+    magic: BTreeMap<char, char>,
+    score: f64,
+    /// Each objective as a percentage of characters
+    objectives: BTreeMap<String, f64>,
+}
+
+/// The layouts on a Pareto front, for JSON
+#[derive(Serialize)]
+struct ParetoFront {
+    layouts: Vec<ParetoLayout>,
+}
+
+/// Prints the layouts on a Pareto front, with the stats they were optimised for as a
+/// percentage of characters. Sorted by the first stat
+pub fn print_pareto_front(
+    front: &[Layout],
+    config: &Config,
+    settings: &Settings,
+    format: OutputFormat,
+) {
     #![allow(clippy::cast_precision_loss)]
-    let min_range = 0;
-    let max_range = 10;
-    if !args.compact {
-        let mut builder = Builder::default();
-        builder.push_record([title, "Frequency".to_string()]);
-        for line in vec.iter().take(max_range).skip(min_range) {
+    let names: Vec<&String> = config
+        .pareto
+        .minimise
+        .iter()
+        .chain(&config.pareto.maximise)
+        .collect();
+    let mut layouts: Vec<(&Layout, Vec<f64>)> = front
+        .iter()
+        .map(|layout| {
+            let values = names
+                .iter()
+                .map(|name| {
+                    let value = layout.stats.counter(name).expect("objectives are stats");
+                    value as f64 * 100.0 / f64::from(layout.stats.chars)
+                })
+                .collect();
+            (layout, values)
+        })
+        .collect();
+    layouts.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]));
+
+    match format {
+        OutputFormat::Json => {
+            let layouts: Vec<ParetoLayout> = layouts
+                .into_iter()
+                .map(|(layout, values)| ParetoLayout {
+                    layout: layout.layout.iter().collect(),
+                    magic: layout.magic.iter().map(|(&k, &v)| (k, v)).collect(),
+                    score: layout.stats.score,
+                    objectives: names
+                        .iter()
+                        .map(|name| (*name).clone())
+                        .zip(values)
+                        .collect(),
+                })
+                .collect();
+            print_json(&Output {
+                settings,
+                result: ParetoFront { layouts },
+            });
+        }
+        OutputFormat::Csv => {
+            // One row per layout, so every row repeats the settings
+            let settings = flatten(&BTreeMap::from([("settings", settings)]));
+            let mut records = vec![["layout".to_owned(), "magic".to_owned()]
+                .into_iter()
+                .chain(names.iter().map(|name| (*name).clone()))
+                .chain(["score".to_owned()])
+                .chain(settings.iter().map(|(field, _)| field.clone()))
+                .collect()];
+            for (layout, values) in layouts {
+                let mut magic = format_magic(&layout.magic);
+                magic.sort();
+                records.push(
+                    [layout.layout.iter().collect(), magic.join(" ")]
+                        .into_iter()
+                        .chain(values.iter().map(f64::to_string))
+                        .chain([layout.stats.score.to_string()])
+                        .chain(settings.iter().map(|(_, value)| value.clone()))
+                        .collect(),
+                );
+            }
+            print_csv(&records);
+        }
+        OutputFormat::Table => {
+            let mut builder = Builder::default();
+            builder.push_record(
+                ["Layout".to_owned(), "Magic".to_owned()]
+                    .into_iter()
+                    .chain(names.iter().map(|name| (*name).clone()))
+                    .chain(["Score".to_owned()]),
+            );
+            for (layout, values) in layouts {
+                let mut magic = format_magic(&layout.magic);
+                magic.sort();
+                builder.push_record(
+                    [format_layout(layout.layout).join("\n"), magic.join(" ")]
+                        .into_iter()
+                        .chain(values.iter().map(|value| format!("{value:.3}")))
+                        .chain([layout.stats.score.to_string()]),
+                );
+            }
+            let mut table = builder.build();
+            table.with(Style::sharp());
+            println!("{table}");
+        }
+    }
+}
+
+/// What the frequencies of listed ngrams are out of
+#[derive(Clone, Copy)]
+enum Total {
+    Chars,
+    Skipgrams,
+    /// Quadgram stats are only counted when turned on in the config
+    Quadgrams,
+}
+
+/// Commands that list the most common examples of a type of ngram, with their titles
+const NGRAM_COMMANDS: [(&str, &str, Total); 32] = [
+    ("sfb", "SFB", Total::Chars),
+    ("sfr", "SFR", Total::Chars),
+    ("sfs", "SFS", Total::Skipgrams),
+    ("lsb", "LSB", Total::Chars),
+    ("lss", "LSS", Total::Skipgrams),
+    ("fsb", "FSB", Total::Chars),
+    ("hsb", "HSB", Total::Chars),
+    ("hss", "HSS", Total::Skipgrams),
+    ("thumbsfb", "Thumb SFB", Total::Chars),
+    ("thumbroll", "Thumb Roll", Total::Chars),
+    ("rowjump", "Row Jump", Total::Chars),
+    ("fss", "FSS", Total::Skipgrams),
+    ("alt", "Alt", Total::Chars),
+    ("inroll", "Inroll", Total::Chars),
+    ("outroll", "Outroll", Total::Chars),
+    ("inthreeroll", "Inthreeroll", Total::Chars),
+    ("outthreeroll", "Outthreeroll", Total::Chars),
+    ("red", "Red", Total::Chars),
+    ("weak", "Weak", Total::Chars),
+    ("pinkyoff", "Pinky Off", Total::Chars),
+    ("sfbred", "SFB Red", Total::Chars),
+    ("badred", "Bad Red", Total::Chars),
+    ("onehand", "Onehand", Total::Chars),
+    ("inoutred", "In-Out Red", Total::Chars),
+    ("thumb", "Thumb", Total::Chars),
+    ("bigrams", "Bigrams", Total::Chars),
+    ("skipgrams", "Skipgrams", Total::Skipgrams),
+    ("trigrams", "Trigrams", Total::Chars),
+    ("altchain", "Alt Chain", Total::Quadgrams),
+    ("rollroll", "Roll Roll", Total::Quadgrams),
+    ("altred", "Alt Red", Total::Quadgrams),
+    ("chainedsfb", "Chained SFB", Total::Quadgrams),
+];
+
+/// Old names of ngram commands, with the commands they stand for
+const NGRAM_ALIASES: [(&str, &str); 1] = [("lsbs", "lsb")];
+
+/// The command stats list ngrams for, following old names to the current ones
+#[must_use]
+pub fn ngram_command_name(command: &str) -> &str {
+    NGRAM_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == command)
+        .map_or(command, |(_, name)| name)
+}
+
+/// The title of a command listing ngrams, and the total its frequencies are out of.
+/// `None` if the command doesn't list ngrams
+#[must_use]
+pub fn ngram_command(command: &str, stats: &Stats) -> Option<(&'static str, u32)> {
+    let (_, title, total) = NGRAM_COMMANDS
+        .iter()
+        .find(|(name, _, _)| *name == command)?;
+    let total = match total {
+        Total::Chars => stats.chars,
+        Total::Skipgrams => stats.skipgrams,
+        Total::Quadgrams => stats.quadgrams,
+    };
+    Some((title, total))
+}
+
+/// Which ngrams the ngram commands list
+#[derive(clap::Args, Debug, Clone)]
+pub struct NgramFilter {
+    /// For ngram lists. How many ngrams to show
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+
+    /// For ngram lists. How many of the most frequent ngrams to leave out
+    #[arg(long, default_value_t = 0)]
+    pub skip: usize,
+
+    /// For ngram lists. Only show ngrams with this letter. Can be given more than once
+    #[arg(long)]
+    pub letter: Vec<char>,
+
+    /// For ngram lists. Only show ngrams with a key on this finger, e.g. `LI` or `rp`.
+    /// Can be given more than once
+    #[arg(long, value_parser = parse_finger)]
+    pub finger: Vec<usize>,
+}
+
+impl Default for NgramFilter {
+    fn default() -> Self {
+        NgramFilter {
+            top: 10,
+            skip: 0,
+            letter: Vec::new(),
+            finger: Vec::new(),
+        }
+    }
+}
+
+impl NgramFilter {
+    /// Whether an ngram has one of the letters and one of the fingers asked for
+    fn matches(&self, ngram: &[char; 4], table: &AHashMap<char, Key>) -> bool {
+        let letter = self.letter.is_empty() || ngram.iter().any(|c| self.letter.contains(c));
+        let finger = self.finger.is_empty()
+            || ngram
+                .iter()
+                .filter_map(|c| table.get(c))
+                .any(|key| self.finger.contains(&key.finger_index()));
+        letter && finger
+    }
+}
+
+/// Index of a finger from its short name, in the order of `Key::finger_index`
+fn parse_finger(finger: &str) -> Result<usize, String> {
+    FINGER_NAMES
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(finger))
+        .ok_or(format!(
+            "unknown finger {finger}, use one of {}",
+            FINGER_NAMES.join(" ")
+        ))
+}
+
+/// One ngram in a list of the most frequent
+#[derive(Serialize, Debug, PartialEq)]
+struct NgramFrequency {
+    ngram: String,
+    count: u32,
+    /// Percentage of the total
+    frequency: f64,
+    /// Percentage of every ngram counted towards the stat
+    of_stat: f64,
+    /// Percentage of the total, of this and every more frequent ngram that was kept by the
+    /// filter, skipped ones included
+    cumulative: f64,
+    /// Percentage of the stat, added up like `cumulative`
+    cumulative_of_stat: f64,
+}
+
+/// The most frequent ngrams of a type, for JSON
+#[derive(Serialize)]
+struct NgramList {
+    stat: String,
+    /// What the frequencies are out of
+    total: u32,
+    /// How often the stat happens, which is what `of_stat` is out of
+    stat_total: u32,
+    ngrams: Vec<NgramFrequency>,
+}
+
+/// The ngrams a filter lists, from a list sorted most frequent first
+fn filter_ngrams(
+    vec: &[([char; 4], u32)],
+    total: u32,
+    layout: &[char; 32],
+    filter: &NgramFilter,
+) -> Vec<NgramFrequency> {
+    #![allow(clippy::cast_precision_loss)]
+    let table = layout_raw_to_table(layout);
+    let stat_total: u32 = vec.iter().map(|(_, count)| count).sum();
+    let percent = |count: u32, total: u32| count as f64 / total as f64 * 100.0;
+    let mut cumulative = 0;
+    vec.iter()
+        .filter(|(ngram, _)| filter.matches(ngram, &table))
+        .map(|&(ngram, count)| {
+            cumulative += count;
+            NgramFrequency {
+                ngram: ngram.iter().collect::<String>().trim_end().to_string(),
+                count,
+                frequency: percent(count, total),
+                of_stat: percent(count, stat_total),
+                cumulative: percent(cumulative, total),
+                cumulative_of_stat: percent(cumulative, stat_total),
+            }
+        })
+        .skip(filter.skip)
+        .take(filter.top)
+        .collect()
+}
+
+/// When the user wants a list of the most frequent of a type of ngram, displays them in a table.
+/// Shorter ngrams are padded with spaces, which are trimmed
+pub fn print_ngrams(
+    vec: &[([char; 4], u32)],
+    ngrams: u32,
+    title: String,
+    layout: &[char; 32],
+    settings: &Settings,
+    args: &Args,
+) {
+    let list = NgramList {
+        stat: title,
+        total: ngrams,
+        stat_total: vec.iter().map(|(_, count)| count).sum(),
+        ngrams: filter_ngrams(vec, ngrams, layout, &args.ngram_filter),
+    };
+    match args.format {
+        OutputFormat::Json => print_json(&Output {
+            settings,
+            result: list,
+        }),
+        OutputFormat::Csv => {
+            let mut records = vec![[
+                "ngram",
+                "count",
+                "frequency",
+                "of_stat",
+                "cumulative",
+                "cumulative_of_stat",
+            ]
+            .map(str::to_owned)
+            .to_vec()];
+            records.extend(list.ngrams.into_iter().map(|line| {
+                vec![
+                    line.ngram,
+                    line.count.to_string(),
+                    format!("{:.3}", line.frequency),
+                    format!("{:.3}", line.of_stat),
+                    format!("{:.3}", line.cumulative),
+                    format!("{:.3}", line.cumulative_of_stat),
+                ]
+            }));
+            print_csv(&records);
+        }
+        OutputFormat::Table if args.compact => {
+            let mut output = String::new();
+            for line in list.ngrams {
+                output.push_str(format!("{}{:.3}\n", line.ngram, line.frequency).as_str());
+            }
+            println!("{output}");
+        }
+        OutputFormat::Table => {
+            let mut builder = Builder::default();
             builder.push_record([
-                line.0.iter().collect(),
-                (line.1 as f32 / ngrams as f32 * 100.0).to_string(),
+                list.stat,
+                "Frequency".to_string(),
+                "Of Stat".to_string(),
+                "Cumulative".to_string(),
+                "Cumulative Of Stat".to_string(),
             ]);
+            for line in list.ngrams {
+                builder.push_record([
+                    line.ngram,
+                    format!("{:.3}", line.frequency),
+                    format!("{:.3}", line.of_stat),
+                    format!("{:.3}", line.cumulative),
+                    format!("{:.3}", line.cumulative_of_stat),
+                ]);
+            }
+            let mut table = builder.build();
+            table.with(Style::sharp());
+            println!("{table}");
+        }
+    }
+}
+
+/// Display information about the corpus itself: its size, most common ngrams,
+/// and which characters weren't on the layout so were dropped
+pub fn print_corpus_stats(corpus: &Corpus, dropped: &[(char, u64)], unfiltered: u64) {
+    #![allow(clippy::cast_precision_loss)]
+    let chars = corpus.chars();
+    let percent = |count: u64, total: u64| (count as f64 / total as f64 * 100.0) as f32;
+    let shift = corpus
+        .ngrams([0])
+        .iter()
+        .find(|(monogram, _)| monogram[0] == '*')
+        .map_or(0, |(_, count)| *count);
+    let dropped_total: u64 = dropped.iter().map(|(_, count)| count).sum();
+
+    let mut summary = Builder::default();
+    summary.push_record(["Characters", &chars.to_string()]);
+    summary.push_record(["Shift (*)", &percent(shift, chars).to_string()]);
+    // N-gram corpora and stdin can't be read again, so how much they dropped isn't known
+    let dropped_summary = if unfiltered == 0 {
+        "unknown".to_string()
+    } else {
+        format!("{dropped_total} ({}%)", percent(dropped_total, unfiltered))
+    };
+    summary.push_record(["Dropped", &dropped_summary]);
+    let mut summary = summary.build();
+    summary.with(Style::sharp());
+
+    let ngram_table = |title: &str, ngrams: Vec<(String, u64)>| {
+        let mut builder = Builder::default();
+        builder.push_record([title, "Frequency"]);
+        for (ngram, count) in ngrams.into_iter().take(10) {
+            builder.push_record([ngram, percent(count, chars).to_string()]);
         }
         let mut table = builder.build();
         table.with(Style::sharp());
-        println!("{table}");
+        table
+    };
+    let monograms = corpus.ngrams([0]).into_iter();
+    let bigrams = corpus.ngrams([1, 0]).into_iter();
+    let skipgrams = corpus.ngrams([2, 0]).into_iter();
+    let trigrams = corpus.ngrams([2, 1, 0]).into_iter();
+
+    let mut coverage = Builder::default();
+    coverage.push_record(["Not on layout", "Count", "Frequency"]);
+    for (ch, count) in dropped {
+        coverage.push_record([
+            format!("{ch:?}"),
+            count.to_string(),
+            percent(*count, unfiltered).to_string(),
+        ]);
+    }
+    let mut coverage = coverage.build();
+    coverage.with(Style::sharp());
+
+    let mut ngram_tables = row![
+        ngram_table(
+            "Monogram",
+            monograms.map(|(m, c)| (m.iter().collect(), c)).collect()
+        ),
+        ngram_table(
+            "Bigram",
+            bigrams.map(|(b, c)| (b.iter().collect(), c)).collect()
+        ),
+        ngram_table(
+            "Skipgram",
+            skipgrams
+                .map(|([a, b], c)| (format!("{a}{SKIPPED}{b}"), c))
+                .collect()
+        ),
+        ngram_table(
+            "Trigram",
+            trigrams.map(|(t, c)| (t.iter().collect(), c)).collect()
+        )
+    ];
+    ngram_tables.with(Style::blank());
+
+    let mut table = col!["Corpus", summary, ngram_tables, "Coverage", coverage];
+    table.with(Style::sharp());
+    println!("{table}");
+}
+
+/// Stats in the general section of the full display
+const GENERAL_STATS: [&str; 20] = [
+    "Score",
+    "Fspeed",
+    "Heatmap",
+    "Alt",
+    "Clean Alt",
+    "Alt SFS",
+    "Alt LS",
+    "Alt Scissor",
+    "SFR",
+    "Row Jump",
+    "Red (incl. weak)",
+    "Weak Red",
+    "In-Out Red",
+    "Pinky Off",
+    "Bad Red",
+    "SFB Red",
+    "Onehand",
+    "Thumb Usage",
+    "Thumb SFB",
+    "Thumb Roll",
+];
+
+/// Stats only counted when quadgrams are turned on
+const QUADGRAM_STATS: [&str; 4] = ["Alt Chain", "Roll Roll", "Alt Red", "Chained SFB"];
+
+/// Stats compared by `compare` besides the general ones
+const COMPARE_STATS: [&str; 17] = [
+    "SFB",
+    "SFS",
+    "LSB",
+    "LSS",
+    "FSB",
+    "FSS",
+    "HSB",
+    "HSS",
+    "FSB Short Up",
+    "FSB Long Up",
+    "HSB Short Up",
+    "HSB Long Up",
+    "Inroll",
+    "Outroll",
+    "In3Roll",
+    "Out3Roll",
+    "Roll",
+];
+
+/// Layouts compared against a baseline, for JSON
+#[derive(Serialize)]
+struct Comparison {
+    baseline: String,
+    layouts: Vec<Report>,
+}
+
+/// A layout being compared, with its stats as displayed
+type Compared<'a> = (&'a str, &'a Layout, AHashMap<String, f64>);
+
+/// Prints several layouts side by side, one column each and one row per stat. Every layout
+/// but the baseline shows how far it is from the baseline, with ▲ above and ▼ below.
+/// Columns can be sorted by one of the stats, highest first unless `ascending`.
+/// Errors if the baseline isn't one of the layouts or the stat to sort by doesn't exist
+pub fn print_comparison(
+    layouts: &[(String, Layout)],
+    baseline: &str,
+    sort: Option<&str>,
+    ascending: bool,
+    settings: &Settings,
+    format: OutputFormat,
+) -> Result<(), String> {
+    let mut hashes: Vec<Compared> = layouts
+        .iter()
+        .map(|(name, layout)| (name.as_str(), layout, get_stats_hash(&layout.stats)))
+        .collect();
+    let base = hashes
+        .iter()
+        .find(|(name, _, _)| *name == baseline)
+        .ok_or(format!("baseline {baseline} isn't one of the layouts"))?
+        .2
+        .clone();
+    if let Some(sort) = sort {
+        sort_comparison(&mut hashes, sort, ascending)?;
+    }
+
+    match format {
+        OutputFormat::Json => {
+            print_json(&Output {
+                settings,
+                result: Comparison {
+                    baseline: baseline.to_owned(),
+                    layouts: hashes
+                        .iter()
+                        .map(|(name, layout, _)| Report::new(name, layout))
+                        .collect(),
+                },
+            });
+            return Ok(());
+        }
+        OutputFormat::Csv => {
+            let columns: Vec<AHashMap<String, String>> = hashes
+                .iter()
+                .map(|(name, layout, _)| {
+                    flatten(&Output {
+                        settings,
+                        result: Report::new(name, layout),
+                    })
+                    .into_iter()
+                    .collect()
+                })
+                .collect();
+            let mut records = vec![["field".to_owned()]
+                .into_iter()
+                .chain(hashes.iter().map(|(name, _, _)| (*name).to_owned()))
+                .collect()];
+            let first = flatten(&Output {
+                settings,
+                result: Report::new(hashes[0].0, hashes[0].1),
+            });
+            for (field, _) in first {
+                let values = columns
+                    .iter()
+                    .map(|column| column.get(&field).cloned().unwrap_or_default());
+                records.push([field.clone()].into_iter().chain(values).collect());
+            }
+            print_csv(&records);
+            return Ok(());
+        }
+        OutputFormat::Table => {}
+    }
+
+    let mut stats: Vec<&str> = GENERAL_STATS.into_iter().chain(COMPARE_STATS).collect();
+    // Quadgrams are only counted when turned on in the config
+    if base.contains_key("Alt Chain") {
+        stats.extend(QUADGRAM_STATS);
+    }
+    let mut builder = Builder::default();
+    builder.push_record(["Stat".to_owned()].into_iter().chain(hashes.iter().map(
+        |(name, _, _)| {
+            if *name == baseline {
+                format!("{name} (baseline)")
+            } else {
+                (*name).to_owned()
+            }
+        },
+    )));
+    for stat in stats {
+        builder.push_record([stat.to_owned()].into_iter().chain(hashes.iter().map(
+            |(name, _, hash)| {
+                let value = hash[stat];
+                let delta = value - base[stat];
+                if *name == baseline || delta == 0.0 {
+                    format_stat(stat, value)
+                } else if delta > 0.0 {
+                    format!("{} ▲{}", format_stat(stat, value), format_stat(stat, delta))
+                } else {
+                    format!(
+                        "{} ▼{}",
+                        format_stat(stat, value),
+                        format_stat(stat, -delta)
+                    )
+                }
+            },
+        )));
+    }
+    let mut table = builder.build();
+    table.with(Style::sharp());
+    println!("{table}");
+    Ok(())
+}
+
+/// Sorts compared layouts by a stat, named as in the table or as in `Stats::counters`
+fn sort_comparison(layouts: &mut [Compared], sort: &str, ascending: bool) -> Result<(), String> {
+    #![allow(clippy::cast_precision_loss)]
+    let value = |(_, layout, hash): &Compared| {
+        hash.get(sort)
+            .copied()
+            .or_else(|| layout.stats.counter(sort).map(|value| value as f64))
+    };
+    let Some(first) = layouts.first() else {
+        return Ok(());
+    };
+    if value(first).is_none() {
+        let mut names: Vec<String> = first.2.keys().cloned().collect();
+        names.sort();
+        names.extend(first.1.stats.counters().into_iter().map(|(name, _)| name));
+        return Err(format!(
+            "can't sort by unknown stat {sort}. Stats are: {}",
+            names.join(", ")
+        ));
+    }
+    layouts.sort_by(|a, b| {
+        let order = value(a)
+            .unwrap_or_default()
+            .total_cmp(&value(b).unwrap_or_default());
+        if ascending {
+            order
+        } else {
+            order.reverse()
+        }
+    });
+    Ok(())
+}
+
+/// Stats that are raw counts rather than percentages or scores
+const COUNTER_STATS: [&str; 3] = ["Fspeed", "Heatmap", "Finger usage penalty"];
+
+/// Format a stat for comparing. Counters and large scores get no decimals, and percentages
+/// and other scores 3
+fn format_stat(stat: &str, value: f64) -> String {
+    if COUNTER_STATS.contains(&stat) || value.abs() >= 1000.0 {
+        format!("{value:.0}")
     } else {
-        let mut output = String::new();
-        for line in vec.iter().take(max_range).skip(min_range) {
-            output.push_str(
-                format!(
-                    "{}{}\n",
-                    line.0.iter().collect::<String>(),
-                    (line.1 as f32 / ngrams as f32 * 100.0)
-                )
-                .as_str(),
-            );
+        format!("{value:.3}")
+    }
+}
+
+/// A key that moved, for JSON
+#[derive(Serialize)]
+struct MovedKey {
+    key: char,
+    /// Finger and row on the old layout
+    old: String,
+    /// Finger and row on the new layout
+    new: String,
+}
+
+/// What changed between two layouts, for JSON
+#[derive(Serialize)]
+struct Diff<'a> {
+    old: Report,
+    new: Report,
+    moved: Vec<MovedKey>,
+    changes: &'a [NgramChange],
+}
+
+/// Prints what changed from the old layout to the new one: which keys moved, every stat side
+/// by side, and the ngrams that changed category with the biggest effect on the score.
+/// An ngram's effect only counts the bigram and trigram stats it adds to, not per-key stats
+/// like heatmap, so the effects don't add up to the change in score
+pub fn print_diff(
+    old: (&str, &Layout),
+    new: (&str, &Layout),
+    moved: &[(char, usize, usize)],
+    changes: &[NgramChange],
+    settings: &Settings,
+    format: OutputFormat,
+) {
+    #![allow(clippy::cast_precision_loss)]
+    let position = |layout: &[char; 32], i: usize| {
+        let key = &layout_raw_to_table(layout)[&layout[i]];
+        format!(
+            "{} {}",
+            FINGER_NAMES[key.finger_index()],
+            ROW_NAMES[usize::from(key.row)]
+        )
+    };
+    let moved: Vec<MovedKey> = moved
+        .iter()
+        .map(|&(key, from, to)| MovedKey {
+            key,
+            old: position(&old.1.layout, from),
+            new: position(&new.1.layout, to),
+        })
+        .collect();
+    let frequency = |change: &NgramChange| change.freq as f64 * 100.0 / new.1.stats.chars as f64;
+
+    match format {
+        OutputFormat::Json => print_json(&Output {
+            settings,
+            result: Diff {
+                old: Report::new(old.0, old.1),
+                new: Report::new(new.0, new.1),
+                moved,
+                changes,
+            },
+        }),
+        OutputFormat::Csv => {
+            let mut records = vec![["ngram", "old", "new", "frequency", "score_delta"]
+                .map(str::to_owned)
+                .to_vec()];
+            for change in changes {
+                records.push(vec![
+                    change.ngram.clone(),
+                    change.old.clone(),
+                    change.new.clone(),
+                    format!("{:.3}", frequency(change)),
+                    change.score_delta.to_string(),
+                ]);
+            }
+            print_csv(&records);
+        }
+        OutputFormat::Table => {
+            let mut moved_table = Builder::default();
+            moved_table.push_record(["Key", old.0, new.0]);
+            for key in moved {
+                moved_table.push_record([key.key.to_string(), key.old, key.new]);
+            }
+            let mut moved_table = moved_table.build();
+            moved_table.with(Style::sharp());
+            println!("{moved_table}");
+
+            let layouts = [
+                (old.0.to_owned(), old.1.clone()),
+                (new.0.to_owned(), new.1.clone()),
+            ];
+            print_comparison(&layouts, old.0, None, false, settings, format)
+                .expect("the old layout is the baseline");
+
+            let mut ngrams = Builder::default();
+            // Only the bigram and trigram stats count, not per-key stats like heatmap
+            ngrams.push_record(["N-gram", "Was", "Now", "Frequency", "Score (ngram only)"]);
+            for change in changes.iter().take(10) {
+                ngrams.push_record([
+                    change.ngram.clone(),
+                    change.old.clone(),
+                    change.new.clone(),
+                    format!("{:.3}", frequency(change)),
+                    format_stat("Score", change.score_delta),
+                ]);
+            }
+            let mut ngrams = ngrams.build();
+            ngrams.with(Style::sharp());
+            println!("{ngrams}");
         }
-        println!("{output}");
     }
 }
 
@@ -40,7 +983,10 @@ pub fn print_ngrams(vec: &[([char; 3], u32)], ngrams: u32, title: String, args:
 pub struct LayoutDisplay<'a> {
     name: &'a str,
     layout: [String; 4],
-    stats: AHashMap<&'a str, f32>,
+    stats: AHashMap<String, f64>,
+    fingers: [(&'a str, [f64; 10]); 4],
+    hands: [f64; 2],
+    rows: [f64; 4],
     magic_rules: Vec<String>,
 }
 impl LayoutDisplay<'_> {
@@ -55,27 +1001,50 @@ impl LayoutDisplay<'_> {
             name,
             layout: format_layout(layout),
             stats: get_stats_hash(stats),
+            fingers: get_finger_stats(stats),
+            hands: stats.hand_usage.map(|usage| percent(usage, stats.chars)),
+            rows: stats.row_usage.map(|usage| percent(usage, stats.chars)),
             magic_rules: format_magic(magic_rules),
         }
     }
-    /// Displays data in a more simple way.
-    // TODO
-    pub fn simple_display(_display: Self) {
-        todo!()
+    /// Display a layout and its main stats in a few lines, for pasting into chat or
+    /// listing many layouts
+    pub fn simple_display(self) {
+        let stat = |name: &str| self.stats[name];
+        println!("{}", self.name);
+        for row in &self.layout {
+            println!("{}", spaced_row(row).trim_end());
+        }
+        if !self.magic_rules.is_empty() {
+            let mut magic_rules = self.magic_rules.clone();
+            magic_rules.sort();
+            println!("magic {}", magic_rules.join(" "));
+        }
+        println!(
+            "SFB {:.2}%  SFS {:.2}%  LSB {:.2}%  FSB {:.2}%  HSB {:.2}%",
+            stat("SFB"),
+            stat("SFS"),
+            stat("LSB"),
+            stat("FSB"),
+            stat("HSB")
+        );
+        println!(
+            "Roll {:.2}% (in {:.2}% out {:.2}%)  Red (incl. weak) {:.2}%  Bad Red {:.2}%  Score {}",
+            stat("Roll"),
+            stat("InrollTal"),
+            stat("OutrollTal"),
+            stat("Red (incl. weak)"),
+            stat("Bad Red"),
+            stat("Score")
+        );
     }
 
     /// Display a lot of information about a layout in a pretty table
     pub fn full(self) {
         let mut layout_builder = Builder::default();
         layout_builder.push_record(["Layout"]);
-        for row in self.layout {
-            let mut row_format: String = String::default();
-            for char in row.chars() {
-                row_format.push(char);
-                row_format.push(' ');
-            }
-            row_format.insert(10, ' ');
-            layout_builder.push_record([&row_format]);
+        for row in &self.layout {
+            layout_builder.push_record([spaced_row(row)]);
         }
 
         for rule in self.magic_rules {
@@ -84,10 +1053,7 @@ impl LayoutDisplay<'_> {
         let mut layout_table = layout_builder.build();
 
         layout_table.with(Style::sharp());
-        let general_stats = vec![
-            "Score", "Fspeed", "Heatmap", "Alt", "SFR", "Red", "Weak Red",
-        ];
-        let general = table_from_hashmap(general_stats, self.stats.clone());
+        let general = table_from_hashmap(GENERAL_STATS.to_vec(), self.stats.clone());
 
         let mut bigram = Builder::default();
         bigram.push_record(["", "Bigram", "Skipgram"]);
@@ -102,7 +1068,7 @@ impl LayoutDisplay<'_> {
                     .to_string()
                     .as_str(),
                 self.stats
-                    .get(&name_s.as_str())
+                    .get(name_s.as_str())
                     .expect("")
                     .to_string()
                     .as_str(),
@@ -111,6 +1077,35 @@ impl LayoutDisplay<'_> {
         let mut bigram_table = bigram.build();
         bigram_table.with(Style::sharp());
 
+        let mut scissors = Builder::default();
+        scissors.push_record(["", "Short Up", "Long Up"]);
+        for stat in ["FSB", "HSB"] {
+            scissors.push_record([
+                stat.to_owned(),
+                self.stats[&format!("{stat} Short Up")].to_string(),
+                self.stats[&format!("{stat} Long Up")].to_string(),
+            ]);
+        }
+        let mut scissor_table = scissors.build();
+        scissor_table.with(Style::sharp());
+
+        let mut distances = Builder::default();
+        distances.push_record(["Distance", "SFS", "LSS", "FSS", "HSS"]);
+        for distance in 1.. {
+            let Some(sfs) = self.stats.get(&format!("SFS@{distance}")) else {
+                break;
+            };
+            distances.push_record([
+                distance.to_string(),
+                sfs.to_string(),
+                self.stats[&format!("LSS@{distance}")].to_string(),
+                self.stats[&format!("FSS@{distance}")].to_string(),
+                self.stats[&format!("HSS@{distance}")].to_string(),
+            ]);
+        }
+        let mut distance_table = distances.build();
+        distance_table.with(Style::sharp());
+
         let mut roll = Builder::default();
         roll.push_record(["", "Inwards", "Outwards", "Total"]);
         roll.push_record([
@@ -134,16 +1129,53 @@ impl LayoutDisplay<'_> {
         let mut roll_table = roll.build();
         roll_table.with(Style::sharp());
 
-        let mut table = col![
-            self.name,
-            layout_table,
-            "General",
-            general,
-            "Bigram and Skipgram",
-            bigram_table,
-            "Rolls",
-            roll_table
+        let mut finger = Builder::default();
+        finger.push_record([""].into_iter().chain(FINGER_NAMES));
+        for (stat, values) in self.fingers {
+            // Fspeed is a raw penalty rather than a percentage
+            let precision = if stat == "Fspeed" { 0 } else { 2 };
+            finger.push_record(
+                [stat.to_owned()]
+                    .into_iter()
+                    .chain(values.map(|v| format!("{v:.precision$}"))),
+            );
+        }
+        let mut finger_table = finger.build();
+        finger_table.with(Style::sharp());
+
+        let mut usage = Builder::default();
+        usage.push_record(["Left", "Right"].into_iter().chain(ROW_NAMES));
+        usage.push_record(
+            self.hands
+                .into_iter()
+                .chain(self.rows)
+                .map(|v| format!("{v:.2}")),
+        );
+        let mut usage_table = usage.build();
+        usage_table.with(Style::sharp());
+
+        let mut sections = vec![
+            (self.name.to_owned(), layout_table),
+            ("General".to_owned(), general),
+            ("Bigram and Skipgram".to_owned(), bigram_table),
+            ("Scissor Direction".to_owned(), scissor_table),
+            ("Skipgram Distance".to_owned(), distance_table),
+            ("Rolls".to_owned(), roll_table),
+            ("Fingers".to_owned(), finger_table),
+            ("Hands and Rows".to_owned(), usage_table),
         ];
+        // Quadgrams are only counted when turned on in the config
+        if self.stats.contains_key("Alt Chain") {
+            let quadgram_table = table_from_hashmap(QUADGRAM_STATS.to_vec(), self.stats.clone());
+            sections.push(("Quadgrams".to_owned(), quadgram_table));
+        }
+
+        let mut builder = Builder::default();
+        for (title, section) in sections {
+            builder.push_record([title]);
+            builder.push_record([section.to_string()]);
+        }
+        let mut table = builder.build();
         table.with(Style::sharp());
         println!("{table}");
     }
@@ -159,6 +1191,17 @@ fn format_layout(layout: [char; 32]) -> [String; 4] {
     ]
 }
 
+/// Space out a row of a layout for display, with a gap between the hands
+fn spaced_row(row: &str) -> String {
+    let mut row_format: String = String::default();
+    for char in row.chars() {
+        row_format.push(char);
+        row_format.push(' ');
+    }
+    row_format.insert(10, ' ');
+    row_format
+}
+
 /// Format magic rules for display
 fn format_magic(magic_rules: &AHashMap<char, char>) -> Vec<String> {
     magic_rules
@@ -168,7 +1211,7 @@ fn format_magic(magic_rules: &AHashMap<char, char>) -> Vec<String> {
 }
 
 /// Get a table for display from the ngram HashMap
-fn table_from_hashmap(stats: Vec<&str>, hash: AHashMap<&str, f32>) -> tabled::Table {
+fn table_from_hashmap(stats: Vec<&str>, hash: AHashMap<String, f64>) -> tabled::Table {
     let mut builder = Builder::default();
 
     for stat in stats {
@@ -179,62 +1222,393 @@ fn table_from_hashmap(stats: Vec<&str>, hash: AHashMap<&str, f32>) -> tabled::Ta
     table
 }
 
-/// Turn the stats into a hashmap for display. I don't know why this is needed tbh
-fn get_stats_hash(stats: &Stats) -> AHashMap<&str, f32> {
+/// Percentage of `total` that `count` makes up
+fn percent(count: i64, total: u32) -> f64 {
+    #![allow(clippy::cast_precision_loss)]
+    count as f64 * 100.0 / total as f64
+}
+
+/// Get the per finger stats for display, from left pinky to right pinky
+fn get_finger_stats(stats: &Stats) -> [(&str, [f64; 10]); 4] {
+    #![allow(clippy::cast_precision_loss)]
+    [
+        ("Usage", stats.finger_usage.map(|n| percent(n, stats.chars))),
+        ("SFB", stats.finger_sfb.map(|n| percent(n, stats.chars))),
+        ("SFS", stats.finger_sfs.map(|n| percent(n, stats.skipgrams))),
+        ("Fspeed", stats.finger_fspeed.map(|n| n as f64)),
+    ]
+}
+
+/// Turn the stats into a hashmap for display. I don't know why this is needed tbh.
+/// Skipgrams at each distance are listed as `SFS@1`, `SFS@2` and so on. `SFS` and the like
+/// are the closest skipgrams, as the totals in `Stats` are weighted over every distance
+fn get_stats_hash(stats: &Stats) -> AHashMap<String, f64> {
     #![allow(clippy::cast_precision_loss)]
-    AHashMap::from([
-        ("SFB", stats.sfb as f32 * 100.0 / stats.chars as f32),
-        ("SFR", stats.sfr as f32 * 100.0 / stats.chars as f32),
-        ("SFS", stats.sfs as f32 * 100.0 / stats.skipgrams as f32),
-        ("LSB", stats.lsb as f32 * 100.0 / stats.chars as f32),
-        ("LSS", stats.lss as f32 * 100.0 / stats.skipgrams as f32),
-        ("HSB", stats.hsb as f32 * 100.0 / stats.chars as f32),
-        ("HSS", stats.hss as f32 * 100.0 / stats.skipgrams as f32),
-        ("FSB", stats.fsb as f32 * 100.0 / stats.chars as f32),
-        ("FSS", stats.fss as f32 * 100.0 / stats.skipgrams as f32),
-        ("Alt", stats.alt as f32 * 100.0 / stats.chars as f32),
-        ("Inroll", stats.inroll as f32 * 100.0 / stats.chars as f32),
-        ("Outroll", stats.outroll as f32 * 100.0 / stats.chars as f32),
+    let closest = &stats.skip_stats[0];
+    let mut hash: AHashMap<String, f64> = [
+        ("SFB", stats.sfb as f64 * 100.0 / stats.chars as f64),
+        ("SFR", stats.sfr as f64 * 100.0 / stats.chars as f64),
+        ("SFS", percent(closest.sfs, stats.skipgrams)),
+        ("LSB", stats.lsb as f64 * 100.0 / stats.chars as f64),
+        ("LSS", percent(closest.lss, stats.skipgrams)),
+        ("HSB", stats.hsb as f64 * 100.0 / stats.chars as f64),
+        ("HSS", percent(closest.hss, stats.skipgrams)),
+        ("FSB", stats.fsb as f64 * 100.0 / stats.chars as f64),
+        ("FSB Short Up", percent(stats.fsb_short_up, stats.chars)),
+        ("Thumb Usage", percent(stats.thumb_usage, stats.chars)),
+        ("Thumb SFB", percent(stats.thumb_sfb, stats.chars)),
+        ("Thumb Roll", percent(stats.thumb_roll, stats.chars)),
+        ("FSB Long Up", percent(stats.fsb_long_up, stats.chars)),
+        ("HSB Short Up", percent(stats.hsb_short_up, stats.chars)),
+        ("HSB Long Up", percent(stats.hsb_long_up, stats.chars)),
+        (
+            "Row Jump",
+            stats.row_jump as f64 * 100.0 / stats.chars as f64,
+        ),
+        ("FSS", percent(closest.fss, stats.skipgrams)),
+        ("Alt", stats.alt as f64 * 100.0 / stats.chars as f64),
+        (
+            "Clean Alt",
+            stats.alt_clean as f64 * 100.0 / stats.chars as f64,
+        ),
+        ("Alt SFS", stats.alt_sfs as f64 * 100.0 / stats.chars as f64),
+        (
+            "Alt LS",
+            stats.alt_lateral as f64 * 100.0 / stats.chars as f64,
+        ),
+        (
+            "Alt Scissor",
+            stats.alt_scissor as f64 * 100.0 / stats.chars as f64,
+        ),
+        ("Inroll", stats.inroll as f64 * 100.0 / stats.chars as f64),
+        ("Outroll", stats.outroll as f64 * 100.0 / stats.chars as f64),
         (
             "2Roll",
-            (stats.inroll + stats.outroll) as f32 * 100.0 / stats.chars as f32,
+            (stats.inroll + stats.outroll) as f64 * 100.0 / stats.chars as f64,
         ),
         (
             "In3Roll",
-            stats.inthreeroll as f32 * 100.0 / stats.chars as f32,
+            stats.inthreeroll as f64 * 100.0 / stats.chars as f64,
         ),
         (
             "Out3Roll",
-            stats.outthreeroll as f32 * 100.0 / stats.chars as f32,
+            stats.outthreeroll as f64 * 100.0 / stats.chars as f64,
         ),
         (
             "3Roll",
-            (stats.inthreeroll + stats.outthreeroll) as f32 * 100.0 / stats.chars as f32,
+            (stats.inthreeroll + stats.outthreeroll) as f64 * 100.0 / stats.chars as f64,
         ),
         (
             "InrollTal",
-            (stats.inroll + stats.inthreeroll) as f32 * 100.0 / stats.chars as f32,
+            (stats.inroll + stats.inthreeroll) as f64 * 100.0 / stats.chars as f64,
         ),
         (
             "OutrollTal",
-            (stats.outroll + stats.outthreeroll) as f32 * 100.0 / stats.chars as f32,
+            (stats.outroll + stats.outthreeroll) as f64 * 100.0 / stats.chars as f64,
         ),
         (
             "Roll",
-            (stats.inroll + stats.outroll + stats.inthreeroll + stats.outthreeroll) as f32 * 100.0
-                / stats.chars as f32,
+            (stats.inroll + stats.outroll + stats.inthreeroll + stats.outthreeroll) as f64 * 100.0
+                / stats.chars as f64,
         ),
         (
             "Weak Red",
-            stats.weak_red as f32 * 100.0 / stats.chars as f32,
+            stats.weak_red as f64 * 100.0 / stats.chars as f64,
+        ),
+        (
+            "Red (incl. weak)",
+            (stats.red + stats.weak_red) as f64 * 100.0 / stats.chars as f64,
         ),
         (
-            "Red",
-            (stats.red + stats.weak_red) as f32 * 100.0 / stats.chars as f32,
-        ),
-        ("Score", stats.score as f32),
-        ("Fspeed", stats.fspeed as f32),
-        ("Heatmap", stats.heatmap as f32),
-        ("Finger usage penalty", stats.column_pen as f32),
-    ])
+            "Pinky Off",
+            stats.pinky_off as f64 * 100.0 / stats.chars as f64,
+        ),
+        ("SFB Red", stats.sfb_red as f64 * 100.0 / stats.chars as f64),
+        ("Bad Red", stats.bad_red as f64 * 100.0 / stats.chars as f64),
+        ("Onehand", stats.onehand as f64 * 100.0 / stats.chars as f64),
+        (
+            "In-Out Red",
+            stats.in_out_red as f64 * 100.0 / stats.chars as f64,
+        ),
+        ("Score", stats.score),
+        ("Fspeed", stats.fspeed as f64),
+        ("Heatmap", stats.heatmap as f64),
+        ("Finger usage penalty", stats.column_pen as f64),
+    ]
+    .into_iter()
+    .map(|(stat, value)| (stat.to_owned(), value))
+    .collect();
+    if stats.quadgrams > 0 {
+        let total = stats.quadgrams as f64;
+        hash.insert(
+            "Alt Chain".to_owned(),
+            stats.alt_chain as f64 * 100.0 / total,
+        );
+        hash.insert(
+            "Roll Roll".to_owned(),
+            stats.roll_roll as f64 * 100.0 / total,
+        );
+        hash.insert("Alt Red".to_owned(), stats.alt_red as f64 * 100.0 / total);
+        hash.insert(
+            "Chained SFB".to_owned(),
+            stats.chained_sfb as f64 * 100.0 / total,
+        );
+    }
+    for (distance, skip_stats) in stats.skip_stats.iter().enumerate() {
+        if skip_stats.skipgrams == 0 {
+            break;
+        }
+        let total = skip_stats.skipgrams as f64;
+        let distance = distance + 1;
+        hash.insert(
+            format!("SFS@{distance}"),
+            skip_stats.sfs as f64 * 100.0 / total,
+        );
+        hash.insert(
+            format!("LSS@{distance}"),
+            skip_stats.lss as f64 * 100.0 / total,
+        );
+        hash.insert(
+            format!("FSS@{distance}"),
+            skip_stats.fss as f64 * 100.0 / total,
+        );
+        hash.insert(
+            format!("HSS@{distance}"),
+            skip_stats.hss as f64 * 100.0 / total,
+        );
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        csv_field, filter_ngrams, flatten, format_stat, get_stats_hash, ngram_command_name,
+        parse_finger, print_comparison, sort_comparison, NgramFilter, OutputFormat, Report,
+        Settings, NGRAM_COMMANDS,
+    };
+    use crate::{
+        config::{parse_config, Config},
+        corpus::{normalise, read_raw_corpus, Corpus, Normalisation},
+        load_layout,
+        stats::{analyze, SKIPPED},
+        Args, Directories, Layout,
+    };
+    use ahash::AHashMap;
+    use clap::Parser;
+    use serde_json::{json, Value};
+
+    /// Whirl and Qwerty analysed on a small corpus
+    fn compared_layouts() -> Vec<(String, Layout)> {
+        let config = Config::default();
+        let corpus = Corpus::from_text("the_fed_ran_deep_in_ice", config.window_width());
+        ["whirl", "qwerty"]
+            .map(|name| {
+                let layout = load_layout(format!("layouts/{name}.txt"));
+                let magic = AHashMap::default();
+                let stats = analyze(&corpus, layout, "compare", &magic, &config);
+                (
+                    name.to_owned(),
+                    Layout {
+                        layout,
+                        magic,
+                        stats,
+                    },
+                )
+            })
+            .to_vec()
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("whirl"), "whirl");
+        assert_eq!(csv_field("x j ; . ,"), "\"x j ; . ,\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_flatten() {
+        let value = json!({
+            "name": "whirl",
+            "settings": {"config": {"quadgrams": true}, "corpus": null},
+            "scores": [1.5, {"sfb": 2}],
+        });
+        let fields = flatten(&value);
+        let fields: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|(path, value)| (path.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("name", "whirl"),
+                ("scores.0", "1.5"),
+                ("scores.1.sfb", "2"),
+                ("settings.config.quadgrams", "true"),
+                ("settings.corpus", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_json() {
+        let layouts = compared_layouts();
+        let (name, layout) = &layouts[0];
+        let json = serde_json::to_string(&Report::new(name, layout)).unwrap();
+        let report: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(report["name"], "whirl");
+        assert_eq!(
+            report["layout"].as_str().unwrap(),
+            layout.layout.iter().collect::<String>()
+        );
+        let counters = report["counters"].as_object().unwrap();
+        for (counter, value) in layout.stats.counters() {
+            assert_eq!(counters[&counter], value, "{counter}");
+        }
+        for finger in ["lp", "lr", "lm", "li", "ri", "rm", "rr", "rp"] {
+            assert!(counters.contains_key(&format!("finger_sfb.{finger}")));
+        }
+        assert!(report["summary"]["SFB"].is_number());
+    }
+
+    #[test]
+    fn test_print_comparison() {
+        let layouts = compared_layouts();
+        let args = Args::parse_from(["chogalyzer", "compare"]);
+        let config = Config::default();
+        let settings = Settings::new(&args, &config);
+        let compare = |baseline, sort| {
+            print_comparison(
+                &layouts,
+                baseline,
+                sort,
+                false,
+                &settings,
+                OutputFormat::Csv,
+            )
+        };
+        assert!(compare("whirl", None).is_ok());
+        assert!(compare("qwerty", Some("SFB")).is_ok());
+        assert!(compare("whirl", Some("sfb")).is_ok());
+        assert!(compare("dvorak", None).is_err());
+        let error = compare("whirl", Some("sbf")).unwrap_err();
+        assert!(error.contains("SFB") && error.contains("finger_sfb.lp"));
+    }
+
+    #[test]
+    fn test_skipgram_headlines() {
+        // The headline skipgram stats are the closest ones, not the weighted totals
+        let config = parse_config("[skipgrams]\nweights = [1.0, 2.0]").unwrap();
+        let corpus = Corpus::from_text("u_e_u__e", config.window_width());
+        let layout = load_layout("layouts/whirl.txt");
+        let stats = analyze(&corpus, layout, "analyze", &AHashMap::default(), &config);
+        assert!(stats.counter("sfs") > Some(stats.skip_stats[0].sfs));
+        let hash = get_stats_hash(&stats);
+        assert_eq!(hash["SFS"], hash["SFS@1"]);
+    }
+
+    #[test]
+    fn test_format_stat() {
+        assert_eq!(format_stat("Heatmap", 493.0), "493");
+        assert_eq!(format_stat("Fspeed", 1234.4), "1234");
+        assert_eq!(format_stat("SFB", 1.5), "1.500");
+        assert_eq!(format_stat("Score", -12.25), "-12.250");
+        assert_eq!(format_stat("Score", 1234.5), "1234");
+    }
+
+    #[test]
+    fn test_sort_comparison() {
+        let layouts = compared_layouts();
+        let mut compared: Vec<_> = layouts
+            .iter()
+            .map(|(name, layout)| (name.as_str(), layout, get_stats_hash(&layout.stats)))
+            .collect();
+        // The score is shown exactly as analysed
+        assert_eq!(compared[0].2["Score"], compared[0].1.stats.score);
+        // Qwerty has more same finger bigrams on this corpus
+        sort_comparison(&mut compared, "sfb", false).unwrap();
+        assert_eq!(compared[0].0, "qwerty");
+        sort_comparison(&mut compared, "SFB", true).unwrap();
+        assert_eq!(compared[0].0, "whirl");
+        assert!(sort_comparison(&mut compared, "sbf", true).is_err());
+    }
+
+    #[test]
+    fn test_ngram_commands() {
+        // Thumb alternation is left out so `thumb` has trigrams to list
+        let config = parse_config("quadgrams = true\n[thumbs]\nalt = false").unwrap();
+        // Repeats are typed with the magic key, so there would be no SFRs with it
+        let layout = load_layout("layouts/whirl.txt").map(|key| if key == '*' { '-' } else { key });
+        let raw: String = read_raw_corpus("mr.txt", &Directories::default())
+            .chars()
+            .take(200_000)
+            .collect();
+        let text = normalise(&raw, &Normalisation::default(), &layout);
+        let corpus = Corpus::from_text(&text, config.window_width());
+        for (command, _, _) in NGRAM_COMMANDS {
+            let stats = analyze(&corpus, layout, command, &AHashMap::default(), &config);
+            assert!(!stats.ngram_table.is_empty(), "{command} lists no ngrams");
+        }
+        // Old names still work
+        assert_eq!(ngram_command_name("lsbs"), "lsb");
+        assert_eq!(ngram_command_name("sfb"), "sfb");
+    }
+
+    #[test]
+    fn test_filter_ngrams() {
+        let layout = load_layout("layouts/whirl.txt");
+        let vec = [
+            (['t', 'h', ' ', ' '], 50),
+            (['e', 'a', ' ', ' '], 30),
+            (['s', 't', ' ', ' '], 15),
+            (['a', 'i', ' ', ' '], 5),
+        ];
+        let all = filter_ngrams(&vec, 1000, &layout, &NgramFilter::default());
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[1].ngram, "ea");
+        assert!((all[1].frequency - 3.0).abs() < 1e-4);
+        assert!((all[1].of_stat - 30.0).abs() < 1e-4);
+        assert!((all[1].cumulative_of_stat - 80.0).abs() < 1e-4);
+        assert!((all[3].cumulative_of_stat - 100.0).abs() < 1e-4);
+
+        let filter = NgramFilter {
+            top: 1,
+            skip: 1,
+            letter: vec!['t'],
+            ..NgramFilter::default()
+        };
+        let listed = filter_ngrams(&vec, 1000, &layout, &filter);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].ngram, "st");
+        // Cumulative counts skipped ngrams that match the filter
+        assert!((listed[0].cumulative_of_stat - 65.0).abs() < 1e-4);
+
+        // `a` is on the right ring finger on Whirl
+        let filter = NgramFilter {
+            finger: vec![parse_finger("rr").unwrap()],
+            ..NgramFilter::default()
+        };
+        let listed = filter_ngrams(&vec, 1000, &layout, &filter);
+        let listed: Vec<&str> = listed.iter().map(|line| line.ngram.as_str()).collect();
+        assert_eq!(listed, ["ea", "ai"]);
+        assert!(parse_finger("thumb").is_err());
+
+        // The skipped letter of a skipgram isn't space on the left thumb
+        let skipgrams = [(['t', SKIPPED, 'h', ' '], 20), (['t', '_', 'h', ' '], 10)];
+        let filter = NgramFilter {
+            finger: vec![parse_finger("lt").unwrap()],
+            ..NgramFilter::default()
+        };
+        let listed = filter_ngrams(&skipgrams, 1000, &layout, &filter);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].ngram, "t_h");
+        // It still counts towards the stat
+        assert!((listed[0].of_stat - 100.0 / 3.0).abs() < 1e-4);
+        let filter = NgramFilter {
+            letter: vec!['_'],
+            ..NgramFilter::default()
+        };
+        assert_eq!(filter_ngrams(&skipgrams, 1000, &layout, &filter).len(), 1);
+    }
 }
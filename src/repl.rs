@@ -0,0 +1,293 @@
+//! Interactive mode for trying out changes to a layout. The corpus stays loaded, so every
+//! change is analysed straight away
+
+use crate::{
+    config::Config,
+    corpus::Corpus,
+    generation::{column_swap, improve_layout},
+    output::{self, LayoutDisplay, Settings},
+    save_layout,
+    stats::analyze,
+    Args, Layout,
+};
+use ahash::AHashMap;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+swap a e            swap two keys
+swapcol 2 3         swap two columns, counted from 0 on the left
+magic t h           magic after t types h
+undo                undo the last change
+stats               show every stat
+ngrams sfb          list the most common ngrams of a type, like the command
+save name.txt       save the layout to the layout directory
+generate-from-here  keep swaps that improve the layout, from where it is now,
+                    with the same magic rules
+help                show this
+quit                stop";
+
+/// Everything that can be typed in
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Swap(char, char),
+    SwapColumns(usize, usize),
+    Magic(char, char),
+    Undo,
+    Stats,
+    Ngrams(String),
+    Save(String),
+    Generate,
+    Help,
+    Quit,
+}
+
+/// Parse one line of input
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let key = |word: &str| {
+        let mut chars = word.chars();
+        match (chars.next(), chars.next()) {
+            (Some(key), None) => Ok(key),
+            _ => Err(format!("{word} isn't one key")),
+        }
+    };
+    let column = |word: &str| match word.parse() {
+        Ok(column) if column < 10 => Ok(column),
+        _ => Err(format!("{word} isn't a column from 0 to 9")),
+    };
+    match words.as_slice() {
+        ["swap", a, b] => Ok(Command::Swap(key(a)?, key(b)?)),
+        ["swapcol", a, b] => Ok(Command::SwapColumns(column(a)?, column(b)?)),
+        ["magic", a, b] => Ok(Command::Magic(key(a)?, key(b)?)),
+        ["undo"] => Ok(Command::Undo),
+        ["stats"] => Ok(Command::Stats),
+        ["ngrams", stat] => Ok(Command::Ngrams((*stat).to_owned())),
+        ["save", name] => Ok(Command::Save((*name).to_owned())),
+        ["generate-from-here"] => Ok(Command::Generate),
+        ["help"] => Ok(Command::Help),
+        ["quit" | "exit"] => Ok(Command::Quit),
+        _ => Err(format!("unknown command {line}, try help")),
+    }
+}
+
+/// A layout being edited, with every earlier version for undoing
+pub struct Session {
+    pub layout: [char; 32],
+    pub magic: AHashMap<char, char>,
+    history: Vec<([char; 32], AHashMap<char, char>)>,
+}
+
+impl Session {
+    #[must_use]
+    pub fn new(layout: [char; 32], magic: AHashMap<char, char>) -> Session {
+        Session {
+            layout,
+            magic,
+            history: Vec::new(),
+        }
+    }
+
+    /// Keep the current version for undoing
+    fn remember(&mut self) {
+        self.history.push((self.layout, self.magic.clone()));
+    }
+
+    /// Swap two keys
+    pub fn swap(&mut self, a: char, b: char) -> Result<(), String> {
+        let position = |key| {
+            self.layout
+                .iter()
+                .position(|&other| other == key)
+                .ok_or(format!("{key} isn't on the layout"))
+        };
+        let (a, b) = (position(a)?, position(b)?);
+        self.remember();
+        self.layout.swap(a, b);
+        Ok(())
+    }
+
+    /// Swap two columns
+    pub fn swap_columns(&mut self, a: usize, b: usize) {
+        self.remember();
+        self.layout = column_swap(self.layout, a, b);
+    }
+
+    /// Add or replace the magic rule for a key
+    pub fn set_magic(&mut self, key: char, rule: char) -> Result<(), String> {
+        if !self.layout.contains(&'*') {
+            return Err("the layout has no magic key (*), so magic rules do nothing".to_owned());
+        }
+        if let Some(missing) = [key, rule].iter().find(|key| !self.layout.contains(key)) {
+            return Err(format!("{missing} isn't on the layout"));
+        }
+        if key == rule {
+            return Err(format!("magic already repeats {key} without a rule"));
+        }
+        self.remember();
+        self.magic.insert(key, rule);
+        Ok(())
+    }
+
+    /// Go back to before the last change
+    pub fn undo(&mut self) -> Result<(), String> {
+        let (layout, magic) = self.history.pop().ok_or("nothing to undo")?;
+        self.layout = layout;
+        self.magic = magic;
+        Ok(())
+    }
+
+    /// Replace the layout, e.g. with a generated one
+    fn replace(&mut self, layout: [char; 32], magic: AHashMap<char, char>) {
+        self.remember();
+        self.layout = layout;
+        self.magic = magic;
+    }
+}
+
+/// Read commands until the input ends or `quit`
+pub fn run(name: &str, session: &mut Session, corpus: &Corpus, config: &Config, args: &Args) {
+    println!("{HELP}");
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("{name}> ");
+        io::stdout().flush().expect("couldn't write prompt");
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line.expect("couldn't read input");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match parse_command(&line) {
+            Ok(Command::Quit) => break,
+            Ok(command) => execute(command, name, session, corpus, config, args),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            println!("{error}");
+        }
+    }
+}
+
+/// Run one command, showing a summary of the layout after changes
+fn execute(
+    command: Command,
+    name: &str,
+    session: &mut Session,
+    corpus: &Corpus,
+    config: &Config,
+    args: &Args,
+) -> Result<(), String> {
+    match command {
+        Command::Swap(a, b) => session.swap(a, b)?,
+        Command::SwapColumns(a, b) => session.swap_columns(a, b),
+        Command::Magic(key, rule) => session.set_magic(key, rule)?,
+        Command::Undo => session.undo()?,
+        Command::Stats => {
+            let layout = Layout {
+                layout: session.layout,
+                magic: session.magic.clone(),
+                stats: analyze(corpus, session.layout, "analyze", &session.magic, config),
+            };
+            output::print_layout(name, &layout, &Settings::new(args, config), args);
+            return Ok(());
+        }
+        Command::Ngrams(command) => {
+            let command = output::ngram_command_name(&command);
+            let stats = analyze(corpus, session.layout, command, &session.magic, config);
+            let (title, total) = output::ngram_command(command, &stats)
+                .ok_or(format!("{command} doesn't list ngrams"))?;
+            let mut ngrams: Vec<([char; 4], u32)> = stats.ngram_table.into_iter().collect();
+            ngrams.sort_by_key(|b| std::cmp::Reverse(b.1));
+            let settings = Settings::new(args, config);
+            output::print_ngrams(
+                &ngrams,
+                total,
+                title.to_owned(),
+                &session.layout,
+                &settings,
+                args,
+            );
+            return Ok(());
+        }
+        Command::Save(file) => {
+            let path = args.dirs.layout_dir.join(file);
+            save_layout(&path, &session.layout, &session.magic)
+                .map_err(|error| format!("couldn't save {}: {error}", path.display()))?;
+            println!("saved {}", path.display());
+            return Ok(());
+        }
+        Command::Generate => {
+            let stats = analyze(corpus, session.layout, "generate", &session.magic, config);
+            let layout = Layout {
+                layout: session.layout,
+                magic: session.magic.clone(),
+                stats,
+            };
+            let layout = improve_layout(layout, corpus, config, args.iterations);
+            session.replace(layout.layout, layout.magic);
+        }
+        Command::Help => {
+            println!("{HELP}");
+            return Ok(());
+        }
+        Command::Quit => unreachable!("quit stops before running commands"),
+    }
+    let stats = analyze(corpus, session.layout, "analyze", &session.magic, config);
+    LayoutDisplay::new(name, session.layout, &stats, &session.magic).simple_display();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_command, Command, Session};
+    use crate::load_layout;
+    use ahash::AHashMap;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("swap a e"), Ok(Command::Swap('a', 'e')));
+        assert_eq!(
+            parse_command(" swapcol 2  3"),
+            Ok(Command::SwapColumns(2, 3))
+        );
+        assert_eq!(parse_command("magic t h"), Ok(Command::Magic('t', 'h')));
+        assert_eq!(
+            parse_command("ngrams sfb"),
+            Ok(Command::Ngrams("sfb".to_owned()))
+        );
+        assert_eq!(
+            parse_command("save new.txt"),
+            Ok(Command::Save("new.txt".to_owned()))
+        );
+        assert_eq!(parse_command("generate-from-here"), Ok(Command::Generate));
+        assert!(parse_command("swap ab e").is_err());
+        assert!(parse_command("swapcol 2 10").is_err());
+        assert!(parse_command("swap a").is_err());
+        assert!(parse_command("dance").is_err());
+    }
+
+    #[test]
+    fn test_session() {
+        let layout = load_layout("layouts/whirl.txt");
+        let mut session = Session::new(layout, AHashMap::default());
+        assert!(session.undo().is_err());
+        session.swap('q', 'g').unwrap();
+        assert_eq!(session.layout[0..2], ['g', 'q']);
+        assert!(session.swap('q', '€').is_err());
+        session.swap_columns(0, 1);
+        assert_eq!(session.layout[10..12], [layout[11], layout[10]]);
+        session.set_magic('t', 'h').unwrap();
+        assert_eq!(session.magic[&'t'], 'h');
+        assert!(session.set_magic('q', 'q').is_err());
+        let no_magic = layout.map(|key| if key == '*' { '-' } else { key });
+        let mut no_magic = Session::new(no_magic, AHashMap::default());
+        assert!(no_magic.set_magic('t', 'h').is_err());
+
+        session.undo().unwrap();
+        assert!(session.magic.is_empty());
+        session.undo().unwrap();
+        session.undo().unwrap();
+        assert_eq!(session.layout, layout);
+    }
+}
@@ -1,170 +1,185 @@
 pub mod bigram_stats;
+pub mod quadgram_stats;
 pub mod trigram_stats;
 
-use crate::{Finger, Key, Stats, INCLUDE_THUMB_ALT, INCLUDE_THUMB_ROLL};
+use crate::{config::Config, corpus::Corpus, Finger, Key, Stats, MAX_SKIP};
 use ahash::AHashMap;
+use std::collections::BTreeMap;
+
+/// Stands for the skipped letter of skipgrams listed in `ngram_table`. It's never a key,
+/// unlike `_` for space, so ngram filters can't match it
+pub const SKIPPED: char = '…';
 
 /// Most important function. Analyses the layout including all stats. Also pretty ugly. Bad performance, and very long
 #[must_use]
 pub fn analyze(
-    mut corpus: String,
+    corpus: &Corpus,
     layout_letters: [char; 32],
     command: &str,
     magic_rules: &AHashMap<char, char>,
+    config: &Config,
 ) -> Stats {
     let layout = layout_raw_to_table(&layout_letters);
-    let [mut previous_letter, mut skip_previous_letter] = ['_'; 2];
     let mut stats: Stats = Stats::default();
     let mut char_freq: AHashMap<char, u32> = AHashMap::default();
-    let finger_weights: AHashMap<Finger, i64> = AHashMap::from([
-        (Finger::Pinky, 66),
-        (Finger::Ring, 28),
-        (Finger::Middle, 21),
-        (Finger::Index, 18),
-        (Finger::Thumb, 50),
-    ]);
-
-    if layout_letters.contains(&'*') {
-        for letter in layout_letters {
-            let rule: [char; 2] = match magic_rules.get(&letter) {
-                Some(other_letter) => [letter, *other_letter],
-                None => [letter, letter],
-            };
-            corpus = corpus.replace(&rule.iter().collect::<String>(), &format!("{letter}*"));
-        }
+    let has_magic = layout_letters.contains(&'*');
+    let skip_distances = config.skipgrams.weights.len();
+    // Nothing to count, and an empty corpus has no windows to check the width of
+    if corpus.windows.is_empty() {
+        return stats;
     }
+    assert!(
+        corpus.width() >= config.window_width(),
+        "corpus windows are too narrow for the config"
+    );
+    let mut buffer = [' '; MAX_SKIP + 3];
 
-    for letter_u8 in corpus.as_bytes() {
-        let letter = *letter_u8 as char;
+    for (corpus_window, freq) in &corpus.windows {
+        let window = &mut buffer[..corpus_window.len()];
+        window.copy_from_slice(corpus_window);
+        if has_magic {
+            apply_magic(window, &layout_letters, magic_rules);
+        }
+        let last = window.len() - 1;
+        let (skip_previous_letter, previous_letter, letter) =
+            (window[last - 2], window[last - 1], window[last]);
         let key = &layout[&letter];
         let previous_key = &layout[&previous_letter];
         let skip_previous_key = &layout[&skip_previous_letter];
-        stats.chars += 1;
+        stats.chars += freq;
 
-        *char_freq.entry(letter).or_insert(0) += 1;
+        *char_freq.entry(letter).or_insert(0) += freq;
 
-        let bigram = bigram_stats::bigram_stats(
-            previous_key,
-            key,
-            command,
-            &mut stats,
-            &finger_weights,
-        );
-        if bigram.0 {
+        let bigram =
+            bigram_stats::bigram_stats(previous_key, key, command, &mut stats, config, *freq);
+        // `bigrams`, `skipgrams` and `trigrams` list every ngram of their size
+        if bigram.0 || command == "bigrams" {
             *stats
                 .ngram_table
-                .entry([previous_letter, letter, ' '])
-                .or_insert(0) += 1;
+                .entry([previous_letter, letter, ' ', ' '])
+                .or_insert(0) += freq;
         }
-        let skipgram = bigram_stats::skipgram_stats(
+        for distance in 0..skip_distances {
+            // Windows imported from trigrams are padded with letters that aren't on the layout
+            let Some(skip_key) = layout.get(&window[last - 2 - distance]) else {
+                continue;
+            };
+            let skipgram = bigram_stats::skipgram_stats(
+                skip_key, key, command, &mut stats, config, *freq, distance,
+            );
+            // Only the closest skipgrams are listed
+            if (skipgram || command == "skipgrams") && distance == 0 {
+                *stats
+                    .ngram_table
+                    .entry([skip_previous_letter, SKIPPED, letter, ' '])
+                    .or_insert(0) += freq;
+            }
+        }
+        let trigram = trigram_stats::trigram_stats(
             skip_previous_key,
+            previous_key,
             key,
             command,
-            &mut stats,
-            &finger_weights,
+            stats,
+            &config.thumbs,
+            *freq,
         );
-        if skipgram {
-            *stats
-                .ngram_table
-                .entry([skip_previous_letter, '_', letter])
-                .or_insert(0) += 1;
-        }
-        let trigram =
-            trigram_stats::trigram_stats(skip_previous_key, previous_key, key, command, stats);
         stats = trigram.0;
-        if trigram.1 {
+        if trigram.1 || command == "trigrams" {
             *stats
                 .ngram_table
-                .entry([skip_previous_letter, previous_letter, letter])
-                .or_insert(0) += 1;
+                .entry([skip_previous_letter, previous_letter, letter, ' '])
+                .or_insert(0) += freq;
+        }
+        if config.quadgrams {
+            let first_letter = window[last - 3];
+            if let Some(first_key) = layout.get(&first_letter) {
+                let quadgram = quadgram_stats::quadgram_stats(
+                    first_key,
+                    skip_previous_key,
+                    previous_key,
+                    key,
+                    command,
+                    &mut stats,
+                    &config.thumbs,
+                    *freq,
+                );
+                if quadgram {
+                    *stats
+                        .ngram_table
+                        .entry([first_letter, skip_previous_letter, previous_letter, letter])
+                        .or_insert(0) += freq;
+                }
+            }
         }
-        skip_previous_letter = previous_letter;
-        previous_letter = letter;
     }
-    if !(INCLUDE_THUMB_ALT || INCLUDE_THUMB_ROLL) {
-        stats.chars -= stats.thumb_stat;
+    stats.skipgrams = stats.skip_stats[0].skipgrams;
+    for (skip_stats, weight) in stats.skip_stats.iter().zip(&config.skipgrams.weights) {
+        let weighted = |count: i64| (count as f64 * weight).round() as i64;
+        stats.sfs += weighted(skip_stats.sfs);
+        stats.lss += weighted(skip_stats.lss);
+        stats.fss += weighted(skip_stats.fss);
+        stats.hss += weighted(skip_stats.hss);
     }
-    #[rustfmt::skip]
-    let weighting: [u32; 32] = [
-        12, 4, 3, 6, 7, 7, 6, 3, 4, 12, 
-        3,  1, 0, 0, 6, 6, 0, 0, 1, 3, 
-        8,  9, 8, 4, 9, 9, 4, 8, 9, 8, 
-                  0,       0,
-    ];
-    let max_freq = AHashMap::from([
-        (Finger::Pinky, 7),
-        (Finger::Ring, 12),
-        (Finger::Middle, 13),
-        (Finger::Index, 13),
-        (Finger::Thumb, 25),
-    ]);
-    let mut columns: AHashMap<(Finger, u8), u32> = AHashMap::new();
+    // Highest percentage of use each finger can have before being penalised
+    let max_freq: [u32; 10] = [7, 12, 13, 13, 25, 25, 13, 13, 12, 7];
     for i in 0..layout_letters.len() {
         if char_freq.contains_key(&layout_letters[i]) {
-            stats.heatmap += i64::from(weighting[i] * char_freq[&layout_letters[i]]);
             let key = &layout[&layout_letters[i]];
-            *columns.entry((key.finger.clone(), key.hand)).or_insert(0) +=
-                char_freq[&layout_letters[i]];
+            let freq = i64::from(char_freq[&layout_letters[i]]);
+            stats.heatmap += (config.effort.cost(i, key) * freq as f64).round() as i64;
+            stats.finger_usage[key.finger_index()] += freq;
+            stats.hand_usage[usize::from(key.hand)] += freq;
+            stats.row_usage[usize::from(key.row)] += freq;
+            if key.finger == Finger::Thumb {
+                stats.thumb_usage += freq;
+            }
         }
     }
-    for (column, freq) in columns {
+    for (freq, max_freq) in stats.finger_usage.iter().zip(max_freq) {
         let penalty = f32::max(
-            freq as f32 - max_freq[&column.0] as f32 / 100.0 * stats.chars as f32,
+            *freq as f32 - max_freq as f32 / 100.0 * stats.chars as f32,
             0.0,
         ) as i64;
         stats.column_pen += penalty;
     }
-    let weights = Stats {
-        score: 0.0,
-        heatmap: -5,
-        //heatmap: 0,
-        column_pen: -10000,
-        fspeed: -30,
-        sfb: 0,
-        sfr: 0,
-        sfs: 0,
-        fsb: -500,
-        hsb: -100,
-        hss: -20,
-        fss: -100,
-        lsb: -200,
-        lss: -40,
-        inroll: 100,
-        outroll: 40,
-        alt: 0,
-        inthreeroll: 320,
-        outthreeroll: 160,
-        weak_red: -2000,
-        red: -300,
-        thumb_stat: 0,
-        chars: 0,
-        skipgrams: 0,
-        ngram_table: AHashMap::default(),
-        bad_bigrams: AHashMap::default(),
-    };
-    stats.score = score(&stats, &weights);
+    stats.score = config
+        .scoring
+        .scale
+        .apply(score(&stats, &config.scoring.weights), stats.chars);
     stats
 }
 
-/// Combines all stats into one score with the weighting
+/// Replaces letters typed with the magic key with `*`, the same way the corpus would be
+/// if the magic rules were applied to the whole text. A letter with no rule uses magic to repeat
+pub fn apply_magic(
+    window: &mut [char],
+    layout_letters: &[char; 32],
+    magic_rules: &AHashMap<char, char>,
+) {
+    for letter in layout_letters {
+        let rule = magic_rules.get(letter).unwrap_or(letter);
+        let mut i = 1;
+        while i < window.len() {
+            if window[i - 1] == *letter && window[i] == *rule {
+                window[i] = '*';
+                i += 2;
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Combines all stats into one score with the weights, from the raw counts.
+/// Stats without a weight don't count
 #[must_use]
-pub fn score(stats: &Stats, weighting: &Stats) -> f64 {
-    let mut score = 0;
-    score += stats.fspeed * weighting.fspeed;
-    score += stats.heatmap * weighting.heatmap;
-    score += stats.column_pen * weighting.column_pen;
-    score += stats.lsb * weighting.lsb;
-    score += stats.lss * weighting.lss;
-    score += stats.fsb * weighting.fsb;
-    score += stats.fss * weighting.fss;
-    score += stats.inroll * weighting.inroll;
-    score += stats.inthreeroll * weighting.inthreeroll;
-    score += stats.outroll * weighting.outroll;
-    score += stats.alt * weighting.alt;
-    score += stats.outthreeroll * weighting.outthreeroll;
-    score += stats.weak_red * weighting.weak_red;
-    score += stats.red * weighting.red;
-    score as f64
+pub fn score(stats: &Stats, weights: &BTreeMap<String, f64>) -> f64 {
+    stats
+        .counters()
+        .into_iter()
+        .filter_map(|(name, count)| Some(count as f64 * weights.get(&name)?))
+        .sum()
 }
 
 /// Converts a layout from its raw character form into a Hashmap of keys
@@ -172,43 +187,129 @@ pub fn layout_raw_to_table(layout_raw: &[char; 32]) -> AHashMap<char, Key> {
     #[rustfmt::skip]
     return AHashMap::from([
         // LH top row
-        ( layout_raw[0], Key { hand: 0, finger: Finger::Pinky, row: 0, lateral: false, },),
-        ( layout_raw[1], Key { hand: 0, finger: Finger::Ring, row: 0, lateral: false, },),
-        ( layout_raw[2], Key { hand: 0, finger: Finger::Middle, row: 0, lateral: false, },),
-        ( layout_raw[3], Key { hand: 0, finger: Finger::Index, row: 0, lateral: false, },),
-        ( layout_raw[4], Key { hand: 0, finger: Finger::Index, row: 0, lateral: true, },),
+        ( layout_raw[0], Key { hand: 0, finger: Finger::Pinky, row: 0, column: 0, lateral: false, },),
+        ( layout_raw[1], Key { hand: 0, finger: Finger::Ring, row: 0, column: 1, lateral: false, },),
+        ( layout_raw[2], Key { hand: 0, finger: Finger::Middle, row: 0, column: 2, lateral: false, },),
+        ( layout_raw[3], Key { hand: 0, finger: Finger::Index, row: 0, column: 3, lateral: false, },),
+        ( layout_raw[4], Key { hand: 0, finger: Finger::Index, row: 0, column: 4, lateral: true, },),
         // RH top row
-        ( layout_raw[5], Key { hand: 1, finger: Finger::Index, row: 0, lateral: true, },),
-        ( layout_raw[6], Key { hand: 1, finger: Finger::Index, row: 0, lateral: false, },),
-        ( layout_raw[7], Key { hand: 1, finger: Finger::Middle, row: 0, lateral: false, },),
-        ( layout_raw[8], Key { hand: 1, finger: Finger::Ring, row: 0, lateral: false, },),
-        ( layout_raw[9], Key { hand: 1, finger: Finger::Pinky, row: 0, lateral: false, },),
+        ( layout_raw[5], Key { hand: 1, finger: Finger::Index, row: 0, column: 5, lateral: true, },),
+        ( layout_raw[6], Key { hand: 1, finger: Finger::Index, row: 0, column: 6, lateral: false, },),
+        ( layout_raw[7], Key { hand: 1, finger: Finger::Middle, row: 0, column: 7, lateral: false, },),
+        ( layout_raw[8], Key { hand: 1, finger: Finger::Ring, row: 0, column: 8, lateral: false, },),
+        ( layout_raw[9], Key { hand: 1, finger: Finger::Pinky, row: 0, column: 9, lateral: false, },),
         // LH middle row
-        ( layout_raw[10], Key { hand: 0, finger: Finger::Pinky, row: 1, lateral: false, },),
-        ( layout_raw[11], Key { hand: 0, finger: Finger::Ring, row: 1, lateral: false, },),
-        ( layout_raw[12], Key { hand: 0, finger: Finger::Middle, row: 1, lateral: false, },),
-        ( layout_raw[13], Key { hand: 0, finger: Finger::Index, row: 1, lateral: false, },),
-        ( layout_raw[14], Key { hand: 0, finger: Finger::Index, row: 1, lateral: true, },),
+        ( layout_raw[10], Key { hand: 0, finger: Finger::Pinky, row: 1, column: 0, lateral: false, },),
+        ( layout_raw[11], Key { hand: 0, finger: Finger::Ring, row: 1, column: 1, lateral: false, },),
+        ( layout_raw[12], Key { hand: 0, finger: Finger::Middle, row: 1, column: 2, lateral: false, },),
+        ( layout_raw[13], Key { hand: 0, finger: Finger::Index, row: 1, column: 3, lateral: false, },),
+        ( layout_raw[14], Key { hand: 0, finger: Finger::Index, row: 1, column: 4, lateral: true, },),
         // RH middle row
-        ( layout_raw[15], Key { hand: 1, finger: Finger::Index, row: 1, lateral: true, },),
-        ( layout_raw[16], Key { hand: 1, finger: Finger::Index, row: 1, lateral: false, },),
-        ( layout_raw[17], Key { hand: 1, finger: Finger::Middle, row: 1, lateral: false, },),
-        ( layout_raw[18], Key { hand: 1, finger: Finger::Ring, row: 1, lateral: false, },),
-        ( layout_raw[19], Key { hand: 1, finger: Finger::Pinky, row: 1, lateral: false, },),
+        ( layout_raw[15], Key { hand: 1, finger: Finger::Index, row: 1, column: 5, lateral: true, },),
+        ( layout_raw[16], Key { hand: 1, finger: Finger::Index, row: 1, column: 6, lateral: false, },),
+        ( layout_raw[17], Key { hand: 1, finger: Finger::Middle, row: 1, column: 7, lateral: false, },),
+        ( layout_raw[18], Key { hand: 1, finger: Finger::Ring, row: 1, column: 8, lateral: false, },),
+        ( layout_raw[19], Key { hand: 1, finger: Finger::Pinky, row: 1, column: 9, lateral: false, },),
         // LH bottom row
-        ( layout_raw[20], Key { hand: 0, finger: Finger::Pinky, row: 2, lateral: false, },),
-        ( layout_raw[21], Key { hand: 0, finger: Finger::Ring, row: 2, lateral: false, },),
-        ( layout_raw[22], Key { hand: 0, finger: Finger::Middle, row: 2, lateral: false, },),
-        ( layout_raw[23], Key { hand: 0, finger: Finger::Index, row: 2, lateral: false, },),
-        ( layout_raw[24], Key { hand: 0, finger: Finger::Index, row: 2, lateral: true, },),
+        ( layout_raw[20], Key { hand: 0, finger: Finger::Pinky, row: 2, column: 0, lateral: false, },),
+        ( layout_raw[21], Key { hand: 0, finger: Finger::Ring, row: 2, column: 1, lateral: false, },),
+        ( layout_raw[22], Key { hand: 0, finger: Finger::Middle, row: 2, column: 2, lateral: false, },),
+        ( layout_raw[23], Key { hand: 0, finger: Finger::Index, row: 2, column: 3, lateral: false, },),
+        ( layout_raw[24], Key { hand: 0, finger: Finger::Index, row: 2, column: 4, lateral: true, },),
         // RH bottom row
-        ( layout_raw[25], Key { hand: 1, finger: Finger::Index, row: 2, lateral: true, },),
-        ( layout_raw[26], Key { hand: 1, finger: Finger::Index, row: 2, lateral: false, },),
-        ( layout_raw[27], Key { hand: 1, finger: Finger::Middle, row: 2, lateral: false, },),
-        ( layout_raw[28], Key { hand: 1, finger: Finger::Ring, row: 2, lateral: false, },),
-        ( layout_raw[29], Key { hand: 1, finger: Finger::Pinky, row: 2, lateral: false, },),
+        ( layout_raw[25], Key { hand: 1, finger: Finger::Index, row: 2, column: 5, lateral: true, },),
+        ( layout_raw[26], Key { hand: 1, finger: Finger::Index, row: 2, column: 6, lateral: false, },),
+        ( layout_raw[27], Key { hand: 1, finger: Finger::Middle, row: 2, column: 7, lateral: false, },),
+        ( layout_raw[28], Key { hand: 1, finger: Finger::Ring, row: 2, column: 8, lateral: false, },),
+        ( layout_raw[29], Key { hand: 1, finger: Finger::Pinky, row: 2, column: 9, lateral: false, },),
         // Thumb keys
-        ( layout_raw[30], Key { hand: 0, finger: Finger::Thumb, row: 3, lateral: false, },),
-        ( layout_raw[31], Key { hand: 1, finger: Finger::Thumb, row: 3, lateral: false, },),
+        ( layout_raw[30], Key { hand: 0, finger: Finger::Thumb, row: 3, column: 4, lateral: false, },),
+        ( layout_raw[31], Key { hand: 1, finger: Finger::Thumb, row: 3, column: 5, lateral: false, },),
     ]);
 }
+
+#[cfg(test)]
+mod tests {
+    use ahash::AHashMap;
+
+    use crate::{
+        config::{parse_config, Config},
+        corpus::Corpus,
+        load_layout,
+        stats::{analyze, apply_magic, score, SKIPPED},
+        Stats,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_score() {
+        let stats = Stats {
+            sfb: 10,
+            hsb: 4,
+            finger_sfb: [0, 0, 0, 6, 0, 0, 4, 0, 0, 0],
+            ..Default::default()
+        };
+        let weights = BTreeMap::from([
+            ("sfb".to_owned(), -2.0),
+            ("hsb".to_owned(), -1.5),
+            ("finger_sfb.li".to_owned(), -1.0),
+        ]);
+        assert_eq!(score(&stats, &weights), -20.0 - 6.0 - 6.0);
+        assert_eq!(score(&stats, &BTreeMap::new()), 0.0);
+    }
+
+    #[test]
+    fn test_apply_magic() {
+        let layout = load_layout("layouts/whirl.txt");
+        let rules = AHashMap::from([('t', 'h'), ('h', 'e')]);
+        let cases = [
+            ("athe", "at*e"),
+            ("ell_", "el*_"),
+            ("lll_", "l*l_"),
+            ("_he_", "_h*_"),
+        ];
+        for (window, expected) in cases {
+            let mut window: Vec<char> = window.chars().collect();
+            apply_magic(&mut window, &layout, &rules);
+            assert_eq!(window.iter().collect::<String>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_skipgram_distances() {
+        let layout = load_layout("layouts/whirl.txt");
+        let config = parse_config("[skipgrams]\nweights = [1.0, 2.0]").unwrap();
+        // `u` and `e` are on the same finger, with two letters in between
+        let corpus = Corpus::from_text("uxae", config.window_width());
+        let stats = analyze(&corpus, layout, "analyze", &AHashMap::default(), &config);
+        assert_eq!(stats.skip_stats[0].sfs, 0);
+        assert_eq!(stats.skip_stats[1].sfs, 1);
+        assert_eq!(stats.sfs, 2);
+        assert_eq!(stats.skip_stats[1].skipgrams, 4);
+    }
+
+    #[test]
+    fn test_ngram_table() {
+        let layout = load_layout("layouts/whirl.txt");
+        let config = Config::default();
+        // `u` and `e` are on the same finger
+        let corpus = Corpus::from_text("uxe_ue", config.window_width());
+        let magic = AHashMap::default();
+        let sfb = analyze(&corpus, layout, "sfb", &magic, &config).ngram_table;
+        assert_eq!(sfb.get(&['u', 'e', ' ', ' ']), Some(&1));
+        assert!(sfb.keys().all(|ngram| ngram[1] != SKIPPED));
+        let sfs = analyze(&corpus, layout, "sfs", &magic, &config).ngram_table;
+        assert_eq!(sfs.get(&['u', SKIPPED, 'e', ' ']), Some(&1));
+        assert!(sfs.keys().all(|ngram| ngram[1] == SKIPPED));
+    }
+
+    #[test]
+    fn test_empty_corpus() {
+        let layout = load_layout("layouts/whirl.txt");
+        let config = Config::default();
+        let corpus = Corpus::from_text("", config.window_width());
+        let stats = analyze(&corpus, layout, "analyze", &AHashMap::default(), &config);
+        assert_eq!(stats.chars, 0);
+        assert_eq!(stats.score, 0.0);
+    }
+}
@@ -1,5 +1,4 @@
-use ahash::AHashMap;
-
+use crate::config::Config;
 use crate::Finger;
 use crate::Key;
 use crate::Stats;
@@ -17,53 +16,78 @@ pub enum Bigram {
     HSLSB,
 }
 
+/// Counts the stats of a bigram that occurs `freq` times
 pub fn bigram_stats(
     key1: &Key,
     key2: &Key,
     command: &str,
     stats: &mut Stats,
-    finger_weights: &AHashMap<Finger, i64>,
+    config: &Config,
+    freq: u32,
 ) -> (bool, i64) {
     let stat = bigram_stat(key1, key2);
+    let fspeed = &config.fspeed;
+    let freq = i64::from(freq);
+    // Row jumps overlap with scissors, so are counted on their own
+    let jump = row_jump(key1, key2);
+    if jump {
+        stats.row_jump += freq;
+        let penalty = config.row_jumps.penalty(key1, key2);
+        stats.row_jump_pen += (penalty * freq as f64).round() as i64;
+    }
+    let jump = jump && command == "rowjump";
+    let thumb_sfb = thumb_sfb(key1, key2);
+    if thumb_sfb {
+        stats.thumb_sfb += freq;
+    }
+    let thumb_sfb = thumb_sfb && command == "thumbsfb";
+    if matches!(
+        stat,
+        Bigram::FSB | Bigram::HSB | Bigram::FSLSB | Bigram::HSLSB
+    ) {
+        scissor_direction_stats(key1, key2, stats, freq);
+    }
     // If the command is the stat, we return "true" for inserting the bigram.
-    // We also return a weight
-    match stat {
+    // We also return a weight for one occurrence
+    let (insert, weight) = match stat {
         Bigram::SFB => {
-            stats.sfb += 1;
-            let penalty = 5 * finger_weights[&key1.finger] * distance(key1, key2) as i64;
-            stats.fspeed += penalty;
-            (command == "sfb", 5 * penalty)
+            stats.sfb += freq;
+            stats.finger_sfb[key1.finger_index()] += freq;
+            let penalty = fspeed.penalty(key1, key2, fspeed.sfb);
+            add_fspeed(stats, key1, penalty, freq);
+            (command == "sfb", (5.0 * penalty) as i64)
         }
         Bigram::SFR => {
-            stats.sfr += 1;
-            let penalty = 2 * finger_weights[&key1.finger];
-            stats.fspeed += penalty;
-            (command == "sfr", penalty)
+            stats.sfr += freq;
+            let penalty = fspeed.sfr * fspeed.weights.get(&key1.finger);
+            add_fspeed(stats, key1, penalty, freq);
+            (command == "sfr", penalty as i64)
         }
         Bigram::FSB => {
-            stats.fsb += 1;
+            stats.fsb += freq;
             (command == "fsb", 75)
         }
         Bigram::HSB => {
-            stats.hsb += 1;
+            stats.hsb += freq;
             (command == "hsb", 15)
         }
         Bigram::LSB => {
-            stats.lsb += 1;
+            stats.lsb += freq;
             (command == "lsb", 15)
         }
         Bigram::FSLSB => {
-            stats.fsb += 1;
-            stats.lsb += 1;
+            stats.fsb += freq;
+            stats.lsb += freq;
             (command == "lsb" || command == "fsb", 90)
         }
         Bigram::HSLSB => {
-            stats.hsb += 1;
-            stats.lsb += 1;
+            stats.hsb += freq;
+            stats.lsb += freq;
             (command == "lsb" || command == "hsb", 30)
         }
         Bigram::None => (false, 0),
-    }
+    };
+    (insert || jump || thumb_sfb, weight)
 }
 
 /// Get bigram stats
@@ -82,68 +106,67 @@ pub fn bigram_stat(key1: &Key, key2: &Key) -> Bigram {
         } else {
             // We can't return immediately in case it's multiple stats
             let lsb = ls(key1, key2);
-            let scissor = scissor(key1, key2);
-            if scissor == 1 {
-                if lsb {
-                    Bigram::HSLSB
-                } else {
-                    Bigram::HSB
-                }
-            } else if scissor == 2 {
-                if lsb {
-                    Bigram::FSLSB
-                } else {
-                    Bigram::FSB
-                }
-            } else if lsb {
-                Bigram::LSB
-            } else {
-                Bigram::None
+            match (scissor(key1, key2), lsb) {
+                (Scissor::Half(_), true) => Bigram::HSLSB,
+                (Scissor::Half(_), false) => Bigram::HSB,
+                (Scissor::Full(_), true) => Bigram::FSLSB,
+                (Scissor::Full(_), false) => Bigram::FSB,
+                (Scissor::None, true) => Bigram::LSB,
+                (Scissor::None, false) => Bigram::None,
             }
         }
     } else {
         Bigram::None
     }
 }
-/// Get skipgram stats
+/// Get stats of a skipgram that occurs `freq` times, with `distance + 1` letters in between
 pub fn skipgram_stats(
     key1: &Key,
     key2: &Key,
     command: &str,
     stats: &mut Stats,
-    finger_weights: &AHashMap<Finger, i64>,
+    config: &Config,
+    freq: u32,
+    distance: usize,
 ) -> bool {
     let stat = bigram_stat(key1, key2);
-    stats.skipgrams += 1;
+    let fspeed = &config.fspeed;
+    let weight = config.skipgrams.weights[distance];
+    let skip_stats = &mut stats.skip_stats[distance];
+    skip_stats.skipgrams += freq;
+    let freq = i64::from(freq);
     // Bottom line of each arm corrosponds ot
     match stat {
         Bigram::SFB => {
-            stats.sfs += 1;
-            let penalty = finger_weights[&key1.finger] * distance(key1, key2) as i64;
-            stats.fspeed += penalty;
-            command == "sfb"
+            skip_stats.sfs += freq;
+            if distance == 0 {
+                stats.finger_sfs[key1.finger_index()] += freq;
+            }
+            let penalty = fspeed.penalty(key1, key2, fspeed.sfs * weight);
+            add_fspeed(stats, key1, penalty, freq);
+            command == "sfs"
         }
         Bigram::FSB => {
-            stats.fss += 1;
-            command == "fsb"
+            skip_stats.fss += freq;
+            command == "fss"
         }
         Bigram::HSB => {
-            stats.hss += 1;
-            command == "hsb"
+            skip_stats.hss += freq;
+            command == "hss"
         }
         Bigram::LSB => {
-            stats.lss += 1;
-            command == "lsb"
+            skip_stats.lss += freq;
+            command == "lss"
         }
         Bigram::FSLSB => {
-            stats.fss += 1;
-            stats.lss += 1;
-            command == "lsb" || command == "fsb"
+            skip_stats.fss += freq;
+            skip_stats.lss += freq;
+            command == "lss" || command == "fss"
         }
         Bigram::HSLSB => {
-            stats.hss += 1;
-            stats.lss += 1;
-            command == "lsb" || command == "hsb"
+            skip_stats.hss += freq;
+            skip_stats.lss += freq;
+            command == "lss" || command == "hss"
         }
         // None or SFR
         _ => false,
@@ -155,6 +178,22 @@ pub fn sf(key1: &Key, key2: &Key) -> bool {
     key1.finger == key2.finger && key1.hand == key2.hand && key1 != key2
 }
 
+/// Check whether a bigram goes between the two thumb keys. With one thumb key each,
+/// this is only a same finger bigram if one thumb presses both
+pub fn thumb_sfb(key1: &Key, key2: &Key) -> bool {
+    key1.finger == Finger::Thumb && key2.finger == Finger::Thumb && key1 != key2
+}
+
+/// Check whether a bigram jumps between the top and bottom rows on one hand.
+/// Same finger bigrams are left to SFBs
+pub fn row_jump(key1: &Key, key2: &Key) -> bool {
+    key1.hand == key2.hand
+        && key1.finger != key2.finger
+        && key1.finger != Finger::Thumb
+        && key2.finger != Finger::Thumb
+        && key1.row.abs_diff(key2.row) == 2
+}
+
 /// Check whether bigram is a lateral stretch
 pub fn ls(key1: &Key, key2: &Key) -> bool {
     if (key1.lateral || key2.lateral)
@@ -167,46 +206,89 @@ pub fn ls(key1: &Key, key2: &Key) -> bool {
     false
 }
 
-/// Check the intensity of a scissor.
-/// 0 => not a scissor, 1 => Half Scissor, 2 => Full Scissor
-// TODO maybe turn that into an enum
-// TODO this is really disgusting
-pub fn scissor(key1: &Key, key2: &Key) -> u8 {
-    let distance: u8 = (i64::from(key1.row) - i64::from(key2.row))
-        .abs()
-        .try_into()
-        .expect("invalid distance");
-    if key1.hand == key2.hand
-        && key1.finger != key2.finger
-        && (((key1.finger == Finger::Pinky || key1.finger == Finger::Index)
-            && (key2.finger == Finger::Middle || key2.finger == Finger::Ring))
-            || ((key2.finger == Finger::Pinky || key2.finger == Finger::Index)
-                && (key1.finger == Finger::Middle || key1.finger == Finger::Ring))
-            || (key1.finger == Finger::Middle && key2.finger == Finger::Ring)
-            || (key2.finger == Finger::Middle && key1.finger == Finger::Ring))
+/// How far a scissor stretches
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub enum Scissor {
+    None,
+    /// One row apart
+    Half(ScissorDirection),
+    /// Two rows apart
+    Full(ScissorDirection),
+}
+
+/// Which finger reaches up in a scissor or row jump
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub enum ScissorDirection {
+    /// The shorter finger is on the higher row, which is painful
+    /// e.g. `qc` on Qwerty
+    ShortUp,
+    /// The longer finger is on the higher row, which is comfortable
+    /// e.g. `ex` on Qwerty
+    LongUp,
+}
+
+impl ScissorDirection {
+    /// Direction of two keys on different rows, by `Finger::length`
+    #[must_use]
+    pub fn of(key1: &Key, key2: &Key) -> ScissorDirection {
+        let (top, bottom) = if key1.row < key2.row {
+            (key1, key2)
+        } else {
+            (key2, key1)
+        };
+        if top.finger.length() < bottom.finger.length() {
+            ScissorDirection::ShortUp
+        } else {
+            ScissorDirection::LongUp
+        }
+    }
+}
+
+/// Check the intensity and direction of a scissor.
+/// Scissors are any two fingers on one hand where one is the middle or ring finger
+pub fn scissor(key1: &Key, key2: &Key) -> Scissor {
+    let long = |finger: &Finger| matches!(finger, Finger::Middle | Finger::Ring);
+    if key1.hand != key2.hand
+        || key1.finger == key2.finger
+        || key1.finger == Finger::Thumb
+        || key2.finger == Finger::Thumb
+        || !(long(&key1.finger) || long(&key2.finger))
     {
-        return distance;
+        return Scissor::None;
+    }
+    match key1.row.abs_diff(key2.row) {
+        1 => Scissor::Half(ScissorDirection::of(key1, key2)),
+        2 => Scissor::Full(ScissorDirection::of(key1, key2)),
+        _ => Scissor::None,
     }
-    0
 }
 
-fn distance(key1: &Key, key2: &Key) -> u8 {
-    let distance_y = key1.row.abs_diff(key2.row);
-    // If they're either both lateral or both not lateral,
-    // we don't need to do pythag for distance
-    if key1.lateral == key2.lateral {
-        distance_y
-    // Otherwise, we do need to do pythag
-    } else {
-        (distance_y.pow(2) + 1).isqrt()
+/// Count the direction of a scissor bigram that occurs `freq` times
+fn scissor_direction_stats(key1: &Key, key2: &Key, stats: &mut Stats, freq: i64) {
+    match scissor(key1, key2) {
+        Scissor::Full(ScissorDirection::ShortUp) => stats.fsb_short_up += freq,
+        Scissor::Full(ScissorDirection::LongUp) => stats.fsb_long_up += freq,
+        Scissor::Half(ScissorDirection::ShortUp) => stats.hsb_short_up += freq,
+        Scissor::Half(ScissorDirection::LongUp) => stats.hsb_long_up += freq,
+        Scissor::None => {}
     }
 }
 
+/// Add a same finger penalty that occurs `freq` times to the finger of the key and the total
+pub fn add_fspeed(stats: &mut Stats, key: &Key, penalty: f64, freq: i64) {
+    let penalty = (penalty * freq as f64).round() as i64;
+    stats.fspeed += penalty;
+    stats.finger_fspeed[key.finger_index()] += penalty;
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
+        config::RowJumps,
         load_two_keys,
-        stats::bigram_stats::{bigram_stat, Bigram},
+        stats::bigram_stats::{
+            bigram_stat, row_jump, scissor, thumb_sfb, Bigram, Scissor, ScissorDirection,
+        },
     };
 
     #[test]
@@ -233,6 +315,34 @@ mod tests {
         test_bigram(strings, Bigram::HSB);
     }
 
+    #[test]
+    fn test_scissor_direction() {
+        let cases = [
+            // Pinky or index on top
+            ("qc", Scissor::Full(ScissorDirection::ShortUp)),
+            ("fp", Scissor::Full(ScissorDirection::ShortUp)),
+            ("pf", Scissor::Full(ScissorDirection::ShortUp)),
+            ("nc", Scissor::Half(ScissorDirection::ShortUp)),
+            ("ns", Scissor::None),
+            // Ring on top of middle
+            ("gp", Scissor::Full(ScissorDirection::ShortUp)),
+            ("eo", Scissor::Half(ScissorDirection::ShortUp)),
+            // Middle or ring on top
+            ("dw", Scissor::Full(ScissorDirection::LongUp)),
+            ("cd", Scissor::Full(ScissorDirection::LongUp)),
+            ("db", Scissor::Full(ScissorDirection::LongUp)),
+            ("gn", Scissor::Half(ScissorDirection::LongUp)),
+            ("ru", Scissor::Half(ScissorDirection::LongUp)),
+            ("u.", Scissor::Full(ScissorDirection::LongUp)),
+            // Index and pinky don't scissor
+            ("fb", Scissor::None),
+        ];
+        for (string, expected) in cases {
+            let (key1, key2) = load_two_keys(string);
+            assert_eq!(scissor(&key1, &key2), expected, "{string}");
+        }
+    }
+
     #[test]
     fn test_lsb() {
         let strings = Vec::from(["tm", "sm", "iz", "'e"]);
@@ -251,7 +361,50 @@ mod tests {
         test_bigram(strings, Bigram::HSLSB);
     }
 
-    // TODO add tests for non scissor row jumps
+    #[test]
+    fn test_row_jump() {
+        // Scissors and non scissors alike
+        for string in ["fb", "qw", "kq", "pf", "pv", ",l"] {
+            let (key1, key2) = load_two_keys(string);
+            assert!(row_jump(&key1, &key2), "{string}");
+        }
+        // Same finger, one row apart, other hand or thumb
+        for string in ["dp", "nc", "ft", "fj", "q_"] {
+            let (key1, key2) = load_two_keys(string);
+            assert!(!row_jump(&key1, &key2), "{string}");
+        }
+        // Index to pinky isn't a scissor
+        test_bigram(Vec::from(["fb", "wq"]), Bigram::None);
+    }
+
+    #[test]
+    fn test_thumb_sfb() {
+        for string in ["_*", "*_"] {
+            let (key1, key2) = load_two_keys(string);
+            assert!(thumb_sfb(&key1, &key2), "{string}");
+        }
+        for string in ["__", "_t", "*e"] {
+            let (key1, key2) = load_two_keys(string);
+            assert!(!thumb_sfb(&key1, &key2), "{string}");
+        }
+    }
+
+    #[test]
+    fn test_row_jump_penalty() {
+        let row_jumps = RowJumps::default();
+        let penalty = |string| {
+            let (key1, key2) = load_two_keys(string);
+            row_jumps.penalty(&key1, &key2)
+        };
+        // Direction doesn't depend on which key comes first
+        assert_eq!(penalty("fb"), penalty("bf"));
+        // The pinky reaching up is worse than the index reaching up
+        assert!(penalty("qw") > penalty("fb"));
+        // Fingers next to each other are worse than ones further apart
+        assert!(penalty("gb") > penalty("fb"));
+        assert_eq!(penalty("fb"), row_jumps.long_up);
+    }
+
     #[test]
     fn test_none() {
         let strings = Vec::from(["th", "ou", "dg"]);
@@ -0,0 +1,143 @@
+use crate::config::Thumbs;
+use crate::stats::bigram_stats::sf;
+use crate::stats::trigram_stats::{trigram_stat, Trigram};
+use crate::Finger;
+use crate::Key;
+use crate::Stats;
+
+/// Every state a quadgram can be
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub enum Quadgram {
+    /// Every key on the other hand from the one before
+    /// e.g. `sign` on Qwerty
+    AltChain,
+    /// A roll on one hand straight into a roll on the other
+    /// e.g. `stil` on Qwerty
+    RollRoll,
+    /// Switching hands into a redirect
+    /// e.g. `ifad` on Qwerty
+    AltRed,
+    /// An SFB where the finger is used again straight after
+    /// e.g. `deac` on Qwerty
+    ChainedSfb,
+    Other,
+}
+
+/// Categorises quadgram
+pub fn quadgram_stat(key1: &Key, key2: &Key, key3: &Key, key4: &Key, thumbs: &Thumbs) -> Quadgram {
+    if sf(key1, key2) && sf(key2, key4) {
+        return Quadgram::ChainedSfb;
+    }
+    if trigram_stat(key1, key2, key3, thumbs) == Trigram::Alt
+        && trigram_stat(key2, key3, key4, thumbs) == Trigram::Alt
+    {
+        return Quadgram::AltChain;
+    }
+    if key1.hand != key2.hand
+        && matches!(
+            trigram_stat(key2, key3, key4, thumbs),
+            Trigram::Red
+                | Trigram::WeakRed
+                | Trigram::PinkyOff
+                | Trigram::BadRed
+                | Trigram::InOutRed
+        )
+    {
+        return Quadgram::AltRed;
+    }
+    if key1.hand == key2.hand
+        && key3.hand == key4.hand
+        && key2.hand != key3.hand
+        && key1.finger != key2.finger
+        && key3.finger != key4.finger
+        && (thumbs.roll
+            || ![key1, key2, key3, key4]
+                .iter()
+                .any(|key| key.finger == Finger::Thumb))
+    {
+        return Quadgram::RollRoll;
+    }
+    Quadgram::Other
+}
+
+/// Gets stats from a quadgram that occurs `freq` times
+#[allow(clippy::too_many_arguments)]
+pub fn quadgram_stats(
+    key1: &Key,
+    key2: &Key,
+    key3: &Key,
+    key4: &Key,
+    command: &str,
+    stats: &mut Stats,
+    thumbs: &Thumbs,
+    freq: u32,
+) -> bool {
+    let count = i64::from(freq);
+    stats.quadgrams += freq;
+    match quadgram_stat(key1, key2, key3, key4, thumbs) {
+        Quadgram::AltChain => {
+            stats.alt_chain += count;
+            command == "altchain"
+        }
+        Quadgram::RollRoll => {
+            stats.roll_roll += count;
+            command == "rollroll"
+        }
+        Quadgram::AltRed => {
+            stats.alt_red += count;
+            command == "altred"
+        }
+        Quadgram::ChainedSfb => {
+            stats.chained_sfb += count;
+            command == "chainedsfb"
+        }
+        Quadgram::Other => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::Thumbs,
+        load_four_keys,
+        stats::quadgram_stats::{quadgram_stat, Quadgram},
+    };
+
+    #[test]
+    fn test_alt_chain() {
+        let strings = Vec::from(["dota", "heta", "_*_*"]);
+        test_quadgram(strings, Quadgram::AltChain);
+    }
+
+    #[test]
+    fn test_roll_roll() {
+        let strings = Vec::from(["stre", "thre", "tsai"]);
+        test_quadgram(strings, Quadgram::RollRoll);
+    }
+
+    #[test]
+    fn test_alt_red() {
+        let strings = Vec::from(["tare", "tere", "ends"]);
+        test_quadgram(strings, Quadgram::AltRed);
+    }
+
+    #[test]
+    fn test_chained_sfb() {
+        let strings = Vec::from(["dtep", "uea;"]);
+        test_quadgram(strings, Quadgram::ChainedSfb);
+    }
+
+    #[test]
+    fn test_other_quadgram() {
+        let strings = Vec::from(["eeee", "ment", "tion"]);
+        test_quadgram(strings, Quadgram::Other);
+    }
+
+    fn test_quadgram(strings: Vec<&str>, expected_stat: Quadgram) {
+        for string in strings {
+            let (key1, key2, key3, key4) = load_four_keys(string);
+            let stat = quadgram_stat(&key1, &key2, &key3, &key4, &Thumbs::default());
+            assert_eq!(stat, expected_stat, "{string}");
+        }
+    }
+}
@@ -1,8 +1,8 @@
+use crate::config::Thumbs;
+use crate::stats::bigram_stats::{bigram_stat, sf, Bigram};
 use crate::Finger;
 use crate::Key;
 use crate::Stats;
-use crate::INCLUDE_THUMB_ALT;
-use crate::INCLUDE_THUMB_ROLL;
 
 /// Every state a trigram can be
 #[derive(Debug, Eq, Hash, PartialEq)]
@@ -14,14 +14,44 @@ pub enum Trigram {
     OutThreeRoll,
     WeakRed,
     Red,
+    /// Redirect with the pinky in the middle
+    PinkyOff,
+    /// One hand with a same finger bigram, so neither a roll nor a redirect
+    SfbRed,
+    /// Redirect without the index finger, but with a thumb
+    BadRed,
+    /// Redirect rolling inwards then outwards
+    InOutRed,
     Other,
 }
 
-/// Categorises trigram
-pub fn trigram_stat(key1: &Key, key2: &Key, key3: &Key) -> Trigram {
+/// Every kind of alternation, from how the keys on the same hand relate
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub enum AltKind {
+    Clean,
+    /// The outer keys are a same finger skipgram
+    Sfs,
+    /// The outer keys are a lateral stretch
+    Lateral,
+    /// The outer keys are a scissor, lateral or not
+    Scissor,
+}
+
+/// Categorises an alternation by its outer keys
+pub fn alt_kind(key1: &Key, key3: &Key) -> AltKind {
+    match bigram_stat(key1, key3) {
+        Bigram::SFB => AltKind::Sfs,
+        Bigram::LSB => AltKind::Lateral,
+        Bigram::FSB | Bigram::HSB | Bigram::FSLSB | Bigram::HSLSB => AltKind::Scissor,
+        Bigram::SFR | Bigram::None => AltKind::Clean,
+    }
+}
+
+/// Categorises trigram. Trigrams with thumbs are left as `Other` if `thumbs` excludes them
+pub fn trigram_stat(key1: &Key, key2: &Key, key3: &Key, thumbs: &Thumbs) -> Trigram {
     if key2.hand != key1.hand
         && key2.hand != key3.hand
-        && (INCLUDE_THUMB_ALT
+        && (thumbs.alt
             || !(key1.finger == Finger::Thumb
                 || key2.finger == Finger::Thumb
                 || key3.finger == Finger::Thumb))
@@ -29,20 +59,20 @@ pub fn trigram_stat(key1: &Key, key2: &Key, key3: &Key) -> Trigram {
         return Trigram::Alt;
     }
     if key1.hand == key2.hand && key2.hand == key3.hand {
-        return onehand(key1, key2, key3);
+        return onehand(key1, key2, key3, thumbs);
     }
     if key1.hand == key2.hand {
-        roll(key1, key2)
+        roll(key1, key2, thumbs)
     } else if key2.hand == key3.hand {
-        roll(key2, key3)
+        roll(key2, key3, thumbs)
     } else {
         Trigram::Other
     }
 }
 
 /// Checks which kind of roll two keys are
-fn roll(key1: &Key, key2: &Key) -> Trigram {
-    if !INCLUDE_THUMB_ROLL && (key1.finger == Finger::Thumb || key2.finger == Finger::Thumb) {
+fn roll(key1: &Key, key2: &Key, thumbs: &Thumbs) -> Trigram {
+    if !thumbs.roll && (key1.finger == Finger::Thumb || key2.finger == Finger::Thumb) {
         return Trigram::Other;
     }
     if key1.finger > key2.finger {
@@ -52,87 +82,154 @@ fn roll(key1: &Key, key2: &Key) -> Trigram {
 }
 
 /// Categorises trigrams on one hand
-fn onehand(key1: &Key, key2: &Key, key3: &Key) -> Trigram {
-    if !INCLUDE_THUMB_ROLL
-        && (key1.finger == Finger::Thumb
-            || key2.finger == Finger::Thumb
-            || key3.finger == Finger::Thumb)
-    {
+fn onehand(key1: &Key, key2: &Key, key3: &Key, thumbs: &Thumbs) -> Trigram {
+    let keys = [key1, key2, key3];
+    if !thumbs.roll && keys.iter().any(|key| key.finger == Finger::Thumb) {
         return Trigram::Other;
     }
+    if sf(key1, key2) || sf(key2, key3) {
+        return Trigram::SfbRed;
+    }
     if key1.finger > key2.finger && key2.finger > key3.finger {
         return Trigram::InThreeRoll;
     }
     if key1.finger < key2.finger && key2.finger < key3.finger {
         return Trigram::OutThreeRoll;
     }
-    if key1.finger != Finger::Index
-        && key2.finger != Finger::Index
-        && key3.finger != Finger::Index
-        && key1.finger != Finger::Thumb
-        && key2.finger != Finger::Thumb
-        && key3.finger != Finger::Thumb
-    {
+    // Everything left is a redirect
+    if key2.finger == Finger::Pinky {
+        return Trigram::PinkyOff;
+    }
+    if !keys.iter().any(|key| key.finger == Finger::Index) {
+        if keys.iter().any(|key| key.finger == Finger::Thumb) {
+            return Trigram::BadRed;
+        }
         return Trigram::WeakRed;
     }
+    if key1.finger > key2.finger {
+        return Trigram::InOutRed;
+    }
     Trigram::Red
 }
 
-/// Gets stats from the trigram
+/// Checks whether a roll uses a thumb. The rolling keys are the ones on the same hand
+fn thumb_roll(key1: &Key, key2: &Key, key3: &Key) -> bool {
+    let hand = if key1.hand == key2.hand {
+        key1.hand
+    } else {
+        key3.hand
+    };
+    [key1, key2, key3]
+        .iter()
+        .any(|key| key.hand == hand && key.finger == Finger::Thumb)
+}
+
+/// Gets stats from a trigram that occurs `freq` times
 pub fn trigram_stats(
     key1: &Key,
     key2: &Key,
     key3: &Key,
     command: &str,
     mut stats: Stats,
+    thumbs: &Thumbs,
+    freq: u32,
 ) -> (Stats, bool) {
     let mut insert_ngram = false;
-    match trigram_stat(key1, key2, key3) {
+    let count = i64::from(freq);
+    let stat = trigram_stat(key1, key2, key3, thumbs);
+    if matches!(
+        stat,
+        Trigram::Inroll | Trigram::Outroll | Trigram::InThreeRoll | Trigram::OutThreeRoll
+    ) && thumb_roll(key1, key2, key3)
+    {
+        stats.thumb_roll += count;
+        if command == "thumbroll" {
+            insert_ngram = true;
+        }
+    }
+    // Every trigram on one hand is also a roll or redirect, so this is counted alongside them
+    if key1.hand == key2.hand && key2.hand == key3.hand && stat != Trigram::Other {
+        stats.onehand += count;
+        if command == "onehand" {
+            insert_ngram = true;
+        }
+    }
+    match stat {
         Trigram::Inroll => {
-            stats.inroll += 1;
+            stats.inroll += count;
             if command == "inroll" {
                 insert_ngram = true;
             }
         }
         Trigram::Outroll => {
-            stats.outroll += 1;
+            stats.outroll += count;
             if command == "outroll" {
                 insert_ngram = true;
             }
         }
         Trigram::Alt => {
-            stats.alt += 1;
+            stats.alt += count;
+            match alt_kind(key1, key3) {
+                AltKind::Clean => stats.alt_clean += count,
+                AltKind::Sfs => stats.alt_sfs += count,
+                AltKind::Lateral => stats.alt_lateral += count,
+                AltKind::Scissor => stats.alt_scissor += count,
+            }
             if command == "alt" {
                 insert_ngram = true;
             }
         }
         Trigram::InThreeRoll => {
-            stats.inthreeroll += 1;
+            stats.inthreeroll += count;
             if command == "inthreeroll" {
                 insert_ngram = true;
             }
         }
         Trigram::OutThreeRoll => {
-            stats.outthreeroll += 1;
+            stats.outthreeroll += count;
             if command == "outthreeroll" {
                 insert_ngram = true;
             }
         }
         Trigram::Red => {
-            stats.red += 1;
+            stats.red += count;
             if command == "red" {
                 insert_ngram = true;
             }
         }
         Trigram::WeakRed => {
-            stats.weak_red += 1;
+            stats.weak_red += count;
             if command == "weak" {
                 insert_ngram = true;
             }
         }
+        Trigram::PinkyOff => {
+            stats.pinky_off += count;
+            if command == "pinkyoff" {
+                insert_ngram = true;
+            }
+        }
+        Trigram::SfbRed => {
+            stats.sfb_red += count;
+            if command == "sfbred" {
+                insert_ngram = true;
+            }
+        }
+        Trigram::BadRed => {
+            stats.bad_red += count;
+            if command == "badred" {
+                insert_ngram = true;
+            }
+        }
+        Trigram::InOutRed => {
+            stats.in_out_red += count;
+            if command == "inoutred" {
+                insert_ngram = true;
+            }
+        }
         Trigram::Other => {
-            stats.thumb_stat += 1;
-            if command == "thumb_stat" {
+            stats.thumb_stat += freq;
+            if command == "thumb" {
                 insert_ngram = true;
             }
         }
@@ -143,8 +240,12 @@ pub fn trigram_stats(
 #[cfg(test)]
 mod tests {
     use crate::{
+        config::Thumbs,
         load_three_keys,
-        stats::trigram_stats::{trigram_stat, Trigram},
+        stats::trigram_stats::{
+            alt_kind, thumb_roll, trigram_stat, trigram_stats, AltKind, Trigram,
+        },
+        Stats,
     };
 
     #[test]
@@ -165,12 +266,76 @@ mod tests {
         test_trigram(strings, Trigram::Alt);
     }
 
+    #[test]
+    fn test_alt_kinds() {
+        let cases = [
+            ("tah", AltKind::Clean),
+            ("tat", AltKind::Clean),
+            ("dat", AltKind::Sfs),
+            ("met", AltKind::Lateral),
+            ("paf", AltKind::Scissor),
+        ];
+        for (string, expected_kind) in cases {
+            let (key1, key2, key3) = load_three_keys(string);
+            assert_eq!(
+                trigram_stat(&key1, &key2, &key3, &Thumbs::default()),
+                Trigram::Alt,
+                "{string}"
+            );
+            assert_eq!(alt_kind(&key1, &key3), expected_kind, "{string}");
+        }
+    }
+
     #[test]
     fn test_redirect() {
-        let strings = Vec::from(["ere", "are", "ght", "_th", "*ia"]);
+        let strings = Vec::from(["_th", "lel", "hth"]);
         test_trigram(strings, Trigram::Red);
     }
 
+    #[test]
+    fn test_in_out_red() {
+        let strings = Vec::from(["ere", "are", "ght"]);
+        test_trigram(strings, Trigram::InOutRed);
+    }
+
+    #[test]
+    fn test_pinky_off() {
+        let strings = Vec::from(["sns", "oye", "aie", "*ia"]);
+        test_trigram(strings, Trigram::PinkyOff);
+    }
+
+    #[test]
+    fn test_sfb_red() {
+        let strings = Vec::from(["dth", "rue"]);
+        test_trigram(strings, Trigram::SfbRed);
+    }
+
+    #[test]
+    fn test_bad_red() {
+        let strings = Vec::from(["t_s", "a*e"]);
+        test_trigram(strings, Trigram::BadRed);
+    }
+
+    #[test]
+    fn test_onehand() {
+        // The same key twice is still a redirect, as it always was
+        test_trigram(Vec::from(["ree", "hhh"]), Trigram::Red);
+        test_trigram(Vec::from(["eoo", "ttt"]), Trigram::WeakRed);
+        for (string, onehand) in [("ell", 1), ("nst", 1), ("the", 0), ("_to", 0)] {
+            let (key1, key2, key3) = load_three_keys(string);
+            let (stats, _) = trigram_stats(
+                &key1,
+                &key2,
+                &key3,
+                "onehand",
+                Stats::default(),
+                &Thumbs::default(),
+                1,
+            );
+            assert_eq!(stats.onehand, onehand, "{string}");
+        }
+    }
+
     #[test]
     fn test_weak_red() {
         let strings = Vec::from(["nds", "nts", "yea"]);
@@ -189,11 +354,40 @@ mod tests {
         test_trigram(strings, Trigram::OutThreeRoll);
     }
 
+    #[test]
+    fn test_thumb_roll() {
+        for string in ["_to", "nd_", "_kn", "*ia"] {
+            let (key1, key2, key3) = load_three_keys(string);
+            assert!(thumb_roll(&key1, &key2, &key3), "{string}");
+        }
+        for string in ["the", "er_", "*th"] {
+            let (key1, key2, key3) = load_three_keys(string);
+            assert!(!thumb_roll(&key1, &key2, &key3), "{string}");
+        }
+    }
+
+    #[test]
+    fn test_thumbs_excluded() {
+        let no_thumbs = Thumbs {
+            alt: false,
+            roll: false,
+        };
+        for string in ["_a_", "*_*", "_to", "nd_", "t_s"] {
+            let (key1, key2, key3) = load_three_keys(string);
+            let stat = trigram_stat(&key1, &key2, &key3, &no_thumbs);
+            assert_eq!(stat, Trigram::Other, "{string}");
+        }
+        // Thumbs on the other hand don't matter to rolls
+        let (key1, key2, key3) = load_three_keys("er_");
+        let stat = trigram_stat(&key1, &key2, &key3, &no_thumbs);
+        assert_eq!(stat, Trigram::Inroll);
+    }
+
     fn test_trigram(strings: Vec<&str>, expected_stat: Trigram) {
         for string in strings {
             let (key1, key2, key3) = load_three_keys(string);
-            let stat = trigram_stat(&key1, &key2, &key3);
-            assert_eq!(stat, expected_stat);
+            let stat = trigram_stat(&key1, &key2, &key3, &Thumbs::default());
+            assert_eq!(stat, expected_stat, "{string}");
         }
     }
 }